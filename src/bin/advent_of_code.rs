@@ -1,11 +1,49 @@
-use advent_of_code::{day_1, day_2, day_3, day_4, day_5, day_7, output};
+use advent_of_code::cli::Args;
+use advent_of_code::{
+    day_1, day_2, day_3, day_4, day_5, day_6, day_7, fetch, output, scaffold, submit, watch,
+};
+use std::process;
 
 fn main() {
-    output::wrap_day_results(1, day_1::solve);
-    output::wrap_day_results(2, day_2::solve);
-    output::wrap_day_results(3, day_3::solve);
-    output::wrap_day_results(4, day_4::solve);
-    output::wrap_day_results(5, day_5::solve);
-    output::wrap_day_results(6, day_6::solve);
-    output::wrap_day_results(7, day_7::solve);
+    let args = Args::from_env();
+
+    match args.command() {
+        None if args.flag("watch") => watch::watch(&args, solve),
+        None => solve(&args),
+        Some("fetch") => {
+            if let Err(error) = fetch::fetch(&args) {
+                eprintln!("Could not fetch input: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("submit") => {
+            let solve_selected_day = || {
+                solve(&args);
+                output::take_answers()
+            };
+            if let Err(error) = submit::submit(&args, solve_selected_day) {
+                eprintln!("Could not submit answer: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("new-day") => {
+            if let Err(error) = scaffold::new_day(&args) {
+                eprintln!("Could not create day: {}", error);
+                process::exit(1);
+            }
+        }
+        Some(command) => panic!("unknown command '{}'", command),
+    }
+}
+
+fn solve(args: &Args) {
+    let selected_day = args.value("day");
+
+    output::wrap_selected_day_results(selected_day, 1, || day_1::solve(args));
+    output::wrap_selected_day_results(selected_day, 2, || day_2::solve(args));
+    output::wrap_selected_day_results(selected_day, 3, || day_3::solve(args));
+    output::wrap_selected_day_results(selected_day, 4, || day_4::solve(args));
+    output::wrap_selected_day_results(selected_day, 5, || day_5::solve(args));
+    output::wrap_selected_day_results(selected_day, 6, || day_6::solve(args));
+    output::wrap_selected_day_results(selected_day, 7, || day_7::solve(args));
 }
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+
+/// Command line arguments of the form `--name value`, `--name=value` or `--flag`, optionally
+/// preceded by a command such as `fetch` and its operands, as in `new-day 8`.
+#[derive(Clone)]
+pub struct Args {
+    command: Option<String>,
+    operands: Vec<String>,
+    options: HashMap<String, Option<String>>,
+}
+
+impl Args {
+    pub fn from_env() -> Args {
+        Args::parse(env::args().skip(1))
+    }
+
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Args {
+        let mut options = HashMap::new();
+        let mut args = args.into_iter().peekable();
+        let command = args.next_if(|arg| !arg.starts_with("--"));
+        let mut operands = Vec::new();
+        while let Some(operand) = args.next_if(|arg| !arg.starts_with("--")) {
+            operands.push(operand);
+        }
+
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .unwrap_or_else(|| panic!("unexpected argument '{}'", arg));
+
+            if let Some((name, value)) = name.split_once('=') {
+                options.insert(name.to_string(), Some(value.to_string()));
+                continue;
+            }
+
+            let value = args.next_if(|next| !next.starts_with("--"));
+            options.insert(name.to_string(), value);
+        }
+
+        Args {
+            command,
+            operands,
+            options,
+        }
+    }
+
+    /// A copy of the arguments with `--name` set to `value`, replacing any value it had.
+    pub fn with_value(&self, name: &str, value: &str) -> Args {
+        let mut args = self.clone();
+        args.options
+            .insert(name.to_string(), Some(value.to_string()));
+
+        args
+    }
+
+    pub fn command(&self) -> Option<&str> {
+        self.command.as_deref()
+    }
+
+    pub fn operand<T: FromStr>(&self, index: usize) -> Option<T> {
+        let operand = self.operands.get(index)?;
+        let operand = operand
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid operand '{}'", operand));
+
+        Some(operand)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    pub fn value<T: FromStr>(&self, name: &str) -> Option<T> {
+        let value = self.options.get(name)?.as_ref()?;
+        let value = value
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid value '{}' for --{}", value, name));
+
+        Some(value)
+    }
+}
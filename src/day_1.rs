@@ -1,6 +1,20 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::cli::Args;
+use crate::fetch;
+use crate::output;
+use crate::parse::{Line, ParseError, Separator};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::{env, process};
+
+/// Number of ids per list kept in memory while sorting externally.
+const DEFAULT_CHUNK_SIZE: usize = 1_000_000;
+
+/// Number of sorted runs merged at once, which bounds the number of open files.
+const MAX_MERGED_RUNS: usize = 128;
 
 #[derive(Debug, Clone)]
 struct Input {
@@ -17,33 +31,118 @@ impl Input {
     }
 }
 
-pub fn solve() {
-    let input = get_input();
+pub fn solve(args: &Args) {
+    let path = fetch::selected_input_path(args, 1);
+
+    let validation = if args.flag("lenient") {
+        Validation::Lenient
+    } else {
+        Validation::Strict
+    };
+
+    if args.flag("external") {
+        if args.flag("report") {
+            println!("--report is not supported with --external, as it needs the lists in memory");
+            return;
+        }
+
+        let chunk_size = args.value("chunk-size").unwrap_or(DEFAULT_CHUNK_SIZE);
+        if let Err(error) = solve_puzzle_1_external(&path, chunk_size, validation) {
+            println!("Invalid input: {}", error);
+            return;
+        }
+        solve_puzzle_2_streaming(&path);
+        return;
+    }
+
+    let input = match get_input(&path, validation) {
+        Ok(input) => input,
+        Err(error) => {
+            println!("Invalid input: {}", error);
+            return;
+        }
+    };
     solve_puzzle_1(input.clone());
     solve_puzzle_2(input.clone());
+
+    if args.flag("report") {
+        print_pairs(input.clone());
+        print_contributions(input, args.flag("sort-by-contribution"));
+    }
 }
 
-fn get_input() -> Input {
-    let input = File::open("./src/resources/day_1.txt").expect("input file for day 1 not found");
-    let reader = BufReader::new(input);
+/// How to deal with lines that are not made up of exactly two ids.
+#[derive(Debug, Clone, Copy)]
+enum Validation {
+    /// Stop at the first bad line.
+    Strict,
+    /// Report bad lines and skip them.
+    Lenient,
+}
 
+impl Validation {
+    fn check<T>(self, parsed: Result<T, ParseError>) -> Result<Option<T>, ParseError> {
+        match (parsed, self) {
+            (Ok(parsed), _) => Ok(Some(parsed)),
+            (Err(error), Validation::Strict) => Err(error),
+            (Err(error), Validation::Lenient) => {
+                println!("Skipping {}", error);
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Reads both lists. Every accepted line adds one id to each list, so they always have the same
+/// length.
+fn get_input(path: &Path, validation: Validation) -> Result<Input, ParseError> {
+    parse_input(read_lines(path), validation)
+}
+
+fn parse_input(
+    lines: impl Iterator<Item = String>,
+    validation: Validation,
+) -> Result<Input, ParseError> {
     let mut first_list = Vec::new();
     let mut second_list = Vec::new();
 
-    for line in reader.lines() {
-        let line = line.expect("could not read next line");
-        let mut parts = line.split_whitespace();
+    for pair in parse_pairs(lines) {
+        if let Some((left, right)) = validation.check(pair)? {
+            first_list.push(left);
+            second_list.push(right)
+        }
+    }
+
+    Ok(Input::new(first_list, second_list))
+}
 
-        let left = parts.next().expect("could not get the left number");
-        let left = left.parse().expect("could not parse input");
-        first_list.push(left);
+fn read_pairs(path: &Path) -> impl Iterator<Item = Result<(i64, i64), ParseError>> {
+    parse_pairs(read_lines(path))
+}
 
-        let right = parts.next().expect("could not get the right number");
-        let right = right.parse().expect("could not parse input");
-        second_list.push(right)
-    }
+/// Parses the pairs of ids of all lines that are not blank.
+fn parse_pairs(
+    lines: impl Iterator<Item = String>,
+) -> impl Iterator<Item = Result<(i64, i64), ParseError>> {
+    lines
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_line(Line::new(i + 1, &line)))
+}
 
-    Input::new(first_list, second_list)
+fn read_lines(path: &Path) -> impl Iterator<Item = String> {
+    let input = File::open(path).expect("input file for day 1 not found");
+    let reader = BufReader::new(input);
+
+    reader
+        .lines()
+        .map(|line| line.expect("could not read next line"))
+}
+
+fn parse_line(line: Line) -> Result<(i64, i64), ParseError> {
+    let [left, right] = line.array(Separator::Whitespace)?;
+
+    Ok((left, right))
 }
 
 fn solve_puzzle_1(mut input: Input) {
@@ -57,7 +156,7 @@ fn solve_puzzle_1(mut input: Input) {
         total_distance += distance;
     }
 
-    println!("Puzzle 1: Total distance between lists: {}", total_distance);
+    output::print_answer(1, "Total distance between lists", total_distance);
 }
 
 fn solve_puzzle_2(input: Input) {
@@ -68,13 +167,442 @@ fn solve_puzzle_2(input: Input) {
         *x += 1;
     }
 
-    let mut similarity_score = 0_u64;
+    print_similarity_score(similarity_score(input.first_list, &value_counts));
+}
+
+/// Sums every id of `first_list` times the number of its occurrences in the second list. Ids may
+/// be negative or zero, so the score is signed, and it is computed in 128 bits.
+fn similarity_score(
+    first_list: impl IntoIterator<Item = i64>,
+    value_counts: &HashMap<i64, u64>,
+) -> Result<i128, SimilarityOverflow> {
+    let mut similarity_score = 0_i128;
 
-    for id in input.first_list {
+    for id in first_list {
         if let Some(count) = value_counts.get(&id) {
-            similarity_score += id as u64 * count;
+            similarity_score = add_similarity(similarity_score, id, *count)?;
+        }
+    }
+
+    Ok(similarity_score)
+}
+
+/// Adds `id` times `occurrences` to `score`.
+fn add_similarity(score: i128, id: i64, occurrences: u64) -> Result<i128, SimilarityOverflow> {
+    i128::from(id)
+        .checked_mul(i128::from(occurrences))
+        .and_then(|product| score.checked_add(product))
+        .ok_or(SimilarityOverflow)
+}
+
+fn print_similarity_score(similarity_score: Result<i128, SimilarityOverflow>) {
+    match similarity_score {
+        Ok(similarity_score) => {
+            output::print_answer(2, "Similarity score between lists", similarity_score)
+        }
+        Err(error) => println!("Puzzle 2: {}", error),
+    }
+}
+
+/// The similarity score is outside the range of an `i128`.
+#[derive(Debug)]
+struct SimilarityOverflow;
+
+impl Display for SimilarityOverflow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "similarity score does not fit into 128 bits")
+    }
+}
+
+impl std::error::Error for SimilarityOverflow {}
+
+/// Prints the pairs of the sorted lists together with their distance.
+fn print_pairs(mut input: Input) {
+    input.first_list.sort();
+    input.second_list.sort();
+
+    println!("left\tright\tdistance");
+    for (first, second) in input.first_list.iter().zip(&input.second_list) {
+        println!("{}\t{}\t{}", first, second, i64::abs(first - second));
+    }
+}
+
+/// How much an id of the first list adds to the similarity score.
+struct Contribution {
+    id: i64,
+    /// Number of times the id appears in the second list.
+    occurrences: u64,
+    /// The id times its occurrences, summed over all its appearances in the first list.
+    contribution: i128,
+}
+
+fn similarity_contributions(input: Input) -> Result<Vec<Contribution>, SimilarityOverflow> {
+    let mut value_counts = HashMap::new();
+    for id in input.second_list {
+        *value_counts.entry(id).or_insert(0_u64) += 1;
+    }
+
+    let mut contributions: HashMap<i64, Contribution> = HashMap::new();
+    for id in input.first_list {
+        let occurrences = value_counts.get(&id).copied().unwrap_or(0);
+        let contribution = contributions.entry(id).or_insert(Contribution {
+            id,
+            occurrences,
+            contribution: 0,
+        });
+        contribution.contribution = add_similarity(contribution.contribution, id, occurrences)?;
+    }
+
+    Ok(contributions.into_values().collect())
+}
+
+/// Prints the contribution of every id of the first list, ordered by id or by contribution,
+/// largest first.
+fn print_contributions(input: Input, sort_by_contribution: bool) {
+    let mut contributions = match similarity_contributions(input) {
+        Ok(contributions) => contributions,
+        Err(error) => {
+            println!("Cannot report contributions: {}", error);
+            return;
+        }
+    };
+
+    if sort_by_contribution {
+        contributions
+            .sort_by_key(|contribution| (Reverse(contribution.contribution), contribution.id));
+    } else {
+        contributions.sort_by_key(|contribution| contribution.id);
+    }
+
+    println!("id\toccurrences\tcontribution");
+    for contribution in contributions {
+        println!(
+            "{}\t{}\t{}",
+            contribution.id, contribution.occurrences, contribution.contribution
+        );
+    }
+}
+
+/// Like [`solve_puzzle_1`], but for lists too large for memory. Both lists are sorted with an
+/// external merge sort, keeping at most `chunk_size` ids per list in memory.
+fn solve_puzzle_1_external(
+    path: &Path,
+    chunk_size: usize,
+    validation: Validation,
+) -> Result<(), ParseError> {
+    let dir =
+        TempDir::create(env::temp_dir().join(format!("advent-of-code-day-1-{}", process::id())));
+
+    let mut first_list = ExternalSorter::new(dir.path.join("first"), chunk_size);
+    let mut second_list = ExternalSorter::new(dir.path.join("second"), chunk_size);
+
+    for pair in read_pairs(path) {
+        if let Some((left, right)) = validation.check(pair)? {
+            first_list.push(left);
+            second_list.push(right);
+        }
+    }
+
+    let mut total_distance = 0;
+
+    for (first, second) in first_list.into_sorted().zip(second_list.into_sorted()) {
+        total_distance += i64::abs(first - second);
+    }
+
+    output::print_answer(1, "Total distance between lists", total_distance);
+
+    Ok(())
+}
+
+/// Like [`solve_puzzle_2`], but reads the input twice instead of keeping the lists in memory.
+/// Only the number of occurrences of each distinct id of the second list is kept.
+///
+/// Bad lines are skipped silently, as [`solve_puzzle_1_external`] has already dealt with them.
+fn solve_puzzle_2_streaming(path: &Path) {
+    let mut value_counts = HashMap::new();
+
+    for (_, right) in read_pairs(path).filter_map(Result::ok) {
+        *value_counts.entry(right).or_insert(0_u64) += 1;
+    }
+
+    let first_list = read_pairs(path)
+        .filter_map(Result::ok)
+        .map(|(left, _)| left);
+    print_similarity_score(similarity_score(first_list, &value_counts));
+}
+
+/// A directory that is removed with everything in it when dropped, also when returning early or
+/// panicking.
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn create(path: PathBuf) -> Self {
+        fs::create_dir_all(&path).expect("could not create directory for sorted runs");
+
+        Self { path }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_dir_all(&self.path) {
+            eprintln!("Could not remove {}: {}", self.path.display(), error);
+        }
+    }
+}
+
+/// Sorts more ids than fit in memory by writing sorted runs of `chunk_size` ids to files named
+/// after `path_prefix` and merging them when read back.
+struct ExternalSorter {
+    path_prefix: PathBuf,
+    chunk_size: usize,
+    chunk: Vec<i64>,
+    runs: Vec<PathBuf>,
+    num_created_runs: usize,
+}
+
+impl ExternalSorter {
+    fn new(path_prefix: PathBuf, chunk_size: usize) -> Self {
+        Self {
+            path_prefix,
+            chunk_size: chunk_size.max(1),
+            chunk: Vec::new(),
+            runs: Vec::new(),
+            num_created_runs: 0,
+        }
+    }
+
+    fn push(&mut self, id: i64) {
+        self.chunk.push(id);
+        if self.chunk.len() >= self.chunk_size {
+            self.write_run();
+        }
+    }
+
+    fn write_run(&mut self) {
+        self.chunk.sort();
+
+        let chunk = std::mem::take(&mut self.chunk);
+        let run = self.create_run(chunk.into_iter());
+        self.runs.push(run);
+    }
+
+    fn create_run(&mut self, ids: impl Iterator<Item = i64>) -> PathBuf {
+        let path = PathBuf::from(format!(
+            "{}-{}",
+            self.path_prefix.display(),
+            self.num_created_runs
+        ));
+        self.num_created_runs += 1;
+        let file = File::create(&path).expect("could not create sorted run");
+        let mut writer = BufWriter::new(file);
+
+        for id in ids {
+            writer
+                .write_all(&id.to_le_bytes())
+                .expect("could not write sorted run");
+        }
+        writer.flush().expect("could not write sorted run");
+
+        path
+    }
+
+    fn into_sorted(mut self) -> SortedIds {
+        if !self.chunk.is_empty() {
+            self.write_run();
+        }
+
+        // merge in several passes if there are too many runs to open at once
+        while self.runs.len() > MAX_MERGED_RUNS {
+            let runs = std::mem::take(&mut self.runs);
+            for group in runs.chunks(MAX_MERGED_RUNS) {
+                let merged_run = self.create_run(SortedIds::new(group));
+                for run in group {
+                    fs::remove_file(run).expect("could not remove sorted run");
+                }
+                self.runs.push(merged_run);
+            }
+        }
+
+        SortedIds::new(&self.runs)
+    }
+}
+
+/// The ids of all sorted runs in ascending order, merged lazily.
+struct SortedIds {
+    runs: Vec<BufReader<File>>,
+    /// The smallest id of each run not read yet, together with the index of its run.
+    heads: BinaryHeap<Reverse<(i64, usize)>>,
+}
+
+impl SortedIds {
+    fn new(runs: &[PathBuf]) -> SortedIds {
+        let mut runs: Vec<BufReader<File>> = runs
+            .iter()
+            .map(|path| BufReader::new(File::open(path).expect("could not open sorted run")))
+            .collect();
+
+        let heads = runs
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, run)| Some(Reverse((read_id(run)?, i))))
+            .collect();
+
+        SortedIds { runs, heads }
+    }
+}
+
+impl Iterator for SortedIds {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((id, i)) = self.heads.pop()?;
+
+        if let Some(next_id) = read_id(&mut self.runs[i]) {
+            self.heads.push(Reverse((next_id, i)));
         }
+
+        Some(id)
+    }
+}
+
+fn read_id(run: &mut BufReader<File>) -> Option<i64> {
+    let mut bytes = [0; 8];
+    match run.read_exact(&mut bytes) {
+        Ok(()) => Some(i64::from_le_bytes(bytes)),
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => None,
+        Err(error) => panic!("could not read sorted run: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{Location, ParseErrorKind};
+
+    fn lines(input: &str) -> impl Iterator<Item = String> + '_ {
+        input.lines().map(str::to_string)
+    }
+
+    #[test]
+    fn strict_rejects_third_column() {
+        let error = parse_input(lines("1 2\n3 4 5\n"), Validation::Strict).unwrap_err();
+
+        assert_eq!(error.location, Location { line: 2, column: 1 });
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::WrongCount {
+                expected: 2,
+                found: 3
+            }
+        );
+    }
+
+    #[test]
+    fn strict_rejects_missing_right_value() {
+        let error = parse_input(lines("1 2\n3\n"), Validation::Strict).unwrap_err();
+
+        assert_eq!(error.location, Location { line: 2, column: 1 });
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::WrongCount {
+                expected: 2,
+                found: 1
+            }
+        );
+    }
+
+    #[test]
+    fn strict_rejects_invalid_id() {
+        let error = parse_input(lines("1 2\n3   x\n"), Validation::Strict).unwrap_err();
+
+        assert_eq!(error.location, Location { line: 2, column: 5 });
+        assert_eq!(error.kind, ParseErrorKind::InvalidValue("x".to_string()));
     }
 
-    println!("Puzzle 2: Similarity score between lists: {}", similarity_score);
+    #[test]
+    fn blank_lines_are_skipped() {
+        let input = parse_input(lines("1 2\n\n   \n3 4\n"), Validation::Strict).unwrap();
+
+        assert_eq!(input.first_list, vec![1, 3]);
+        assert_eq!(input.second_list, vec![2, 4]);
+
+        // blank lines still count for the line numbers of errors
+        let error = parse_input(lines("1 2\n\n3\n"), Validation::Strict).unwrap_err();
+        assert_eq!(error.location.line, 3);
+    }
+
+    #[test]
+    fn lenient_skips_bad_lines() {
+        let input = parse_input(lines("1 2\n3\n4 5 6\n7 x\n8 9\n"), Validation::Lenient).unwrap();
+
+        assert_eq!(input.first_list, vec![1, 8]);
+        assert_eq!(input.second_list, vec![2, 9]);
+    }
+
+    #[test]
+    fn similarity_of_negative_ids() {
+        assert_eq!(add_similarity(10, -3, 4).unwrap(), -2);
+        assert_eq!(
+            add_similarity(-2, i64::MIN, 2).unwrap(),
+            -2 + 2 * i128::from(i64::MIN)
+        );
+    }
+
+    #[test]
+    fn similarity_of_zero_ids() {
+        assert_eq!(add_similarity(10, 0, u64::MAX).unwrap(), 10);
+        assert_eq!(add_similarity(10, 7, 0).unwrap(), 10);
+    }
+
+    #[test]
+    fn similarity_overflow() {
+        assert!(add_similarity(i128::MAX, 1, 1).is_err());
+        assert!(add_similarity(i128::MIN, -1, 1).is_err());
+        assert_eq!(add_similarity(i128::MAX, -1, 1).unwrap(), i128::MAX - 1);
+    }
+
+    #[test]
+    fn similarity_of_lists() {
+        let value_counts = HashMap::from([(3, 3), (4, 1), (5, 1), (9, 1)]);
+
+        assert_eq!(
+            similarity_score([3, 4, 2, 1, 3, 3], &value_counts).unwrap(),
+            31
+        );
+        assert!(similarity_score([i64::MAX; 3], &HashMap::from([(i64::MAX, u64::MAX)])).is_err());
+    }
+
+    #[test]
+    fn external_sort_over_several_merge_passes() {
+        let dir = TempDir::create(
+            env::temp_dir().join(format!("advent-of-code-day-1-test-{}", process::id())),
+        );
+        let path = dir.path.clone();
+        // more runs of one id each than can be merged at once
+        let num_ids = 2 * MAX_MERGED_RUNS + 44;
+        let ids: Vec<i64> = (0..num_ids as i64)
+            .map(|i| (i * 7919) % num_ids as i64 - 150)
+            .collect();
+
+        let mut sorter = ExternalSorter::new(path.join("ids"), 1);
+        for id in &ids {
+            sorter.push(*id);
+        }
+        let sorted_ids = sorter.into_sorted();
+
+        assert_eq!(
+            fs::read_dir(&path).unwrap().count(),
+            num_ids.div_ceil(MAX_MERGED_RUNS)
+        );
+
+        let mut expected = ids;
+        expected.sort();
+        assert_eq!(sorted_ids.collect::<Vec<_>>(), expected);
+
+        drop(dir);
+        assert!(!path.exists());
+    }
 }
@@ -1,113 +1,481 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::cli::Args;
+use crate::fetch;
+use crate::output;
+use crate::parse::{self, ParseError, Separator};
+use std::cmp::Reverse;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
 
-#[derive(Clone)]
+/// Levels are signed and wide enough to hold any `i64` or `u64` reading, and the difference of
+/// two levels is computed with `abs_diff`, which cannot overflow.
+type Level = i128;
+
+#[derive(Debug, Clone)]
 struct Input {
-    rows: Vec<Vec<u64>>,
+    rows: Vec<Vec<Level>>,
 }
 
-pub fn solve() {
-    let input = get_input();
+pub fn solve(args: &Args) {
+    let input = match get_input(&fetch::selected_input_path(args, 2)) {
+        Ok(input) => input,
+        Err(error) => {
+            println!("Invalid input: {}", error);
+            return;
+        }
+    };
     solve_puzzle_1(input.clone());
-    solve_puzzle_2(input);
+    solve_puzzle_2(input.clone());
+
+    let policy = SafetyPolicy::from_args(args);
+
+    if let Some(policy) = policy {
+        solve_with_policy(&input, policy);
+    }
+
+    let diagnosis_policy = policy.unwrap_or(SafetyPolicy::DAMPENED);
+
+    if args.flag("diagnose") {
+        print_diagnoses(&input, diagnosis_policy);
+    }
+
+    if let Some(path) = args.value::<PathBuf>("diagnose-csv") {
+        write_diagnoses_csv(&input, diagnosis_policy, path);
+    }
 }
 
 fn solve_puzzle_1(input: Input) {
     let mut num_safe_reports = 0;
 
-    for row in input.rows {
-        if is_safe(row.clone()) {
+    for row in &input.rows {
+        if SafetyPolicy::STRICT.is_safe(row) {
             num_safe_reports += 1;
         }
     }
 
-    println!("Puzzle 1: Number of safe reports: {}", num_safe_reports);
+    output::print_answer(1, "Number of safe reports", num_safe_reports);
 }
 
 fn solve_puzzle_2(input: Input) {
     let mut num_safe_reports = 0;
 
-    for row in input.rows {
-        if is_safe(row.clone()) {
+    for row in &input.rows {
+        if SafetyPolicy::DAMPENED.is_safe(row) {
             num_safe_reports += 1;
-        } else {
-            for i in 0..row.len() {
-                let mut modified_row = row.clone();
-                modified_row.remove(i);
-                if is_safe(modified_row) {
-                    num_safe_reports += 1;
-                    break;
-                }
-            }
         }
     }
 
-    println!("Puzzle 2: Number of safe reports: {}", num_safe_reports);
+    output::print_answer(2, "Number of safe reports", num_safe_reports);
 }
 
+fn solve_with_policy(input: &Input, policy: SafetyPolicy) {
+    let num_safe_reports = input.rows.iter().filter(|row| policy.is_safe(row)).count();
+
+    println!(
+        "Custom policy: Number of safe reports: {}",
+        num_safe_reports
+    );
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
 enum Order {
     Unknown,
     Asc,
     Desc,
 }
 
-fn is_safe(row: Vec<u64>) -> bool {
-    let mut iter = row.into_iter();
-    let mut order = Order::Unknown;
-    if let Some(mut prev_level) = iter.next() {
-        for level in iter {
-            let diff = level as i64 - prev_level as i64;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Monotonicity {
+    /// Every step has to move in the direction of the report.
+    Strict,
+    /// Levels may also stay the same. Such steps are not subject to the step bounds.
+    NonStrict,
+}
+
+/// The rules a report has to follow to be safe.
+#[derive(Debug, Clone, Copy)]
+struct SafetyPolicy {
+    min_step: u128,
+    max_step: u128,
+    monotonicity: Monotonicity,
+    /// How many levels may be removed to make a report safe.
+    max_removals: usize,
+}
+
+impl SafetyPolicy {
+    /// The rules of puzzle 1.
+    const STRICT: SafetyPolicy = SafetyPolicy {
+        min_step: 1,
+        max_step: 3,
+        monotonicity: Monotonicity::Strict,
+        max_removals: 0,
+    };
+
+    /// The rules of puzzle 2, where the Problem Dampener tolerates a single bad level.
+    const DAMPENED: SafetyPolicy = SafetyPolicy {
+        max_removals: 1,
+        ..SafetyPolicy::STRICT
+    };
+
+    /// Builds a policy from `--min-step`, `--max-step`, `--non-strict` and `--removals`, starting
+    /// from the rules of puzzle 1. Returns `None` if none of them is given.
+    fn from_args(args: &Args) -> Option<SafetyPolicy> {
+        let is_custom = ["min-step", "max-step", "non-strict", "removals"]
+            .iter()
+            .any(|name| args.flag(name));
+        if !is_custom {
+            return None;
+        }
+
+        let defaults = SafetyPolicy::STRICT;
+
+        Some(SafetyPolicy {
+            min_step: args.value("min-step").unwrap_or(defaults.min_step),
+            max_step: args.value("max-step").unwrap_or(defaults.max_step),
+            monotonicity: if args.flag("non-strict") {
+                Monotonicity::NonStrict
+            } else {
+                Monotonicity::Strict
+            },
+            max_removals: args.value("removals").unwrap_or(defaults.max_removals),
+        })
+    }
+
+    fn is_safe(&self, row: &[Level]) -> bool {
+        if self.allows_single_removal_check() {
+            return self.find_single_removal(row).is_some();
+        }
 
-            if !(1..=3).contains(&diff.abs()) {
-                return false;
+        self.find_removals(row).is_some()
+    }
+
+    /// Whether [`SafetyPolicy::find_single_removal`] applies, like it does for both puzzles.
+    fn allows_single_removal_check(&self) -> bool {
+        self.max_removals <= 1 && self.monotonicity == Monotonicity::Strict
+    }
+
+    /// Checks a single step of a report going in direction `order`.
+    fn check_step(&self, prev_level: Level, level: Level, order: Order) -> Result<(), Reason> {
+        let step = level.abs_diff(prev_level);
+
+        if step == 0 {
+            return match self.monotonicity {
+                Monotonicity::Strict => Err(Reason::ZeroStep),
+                Monotonicity::NonStrict => Ok(()),
+            };
+        }
+
+        if step > self.max_step {
+            return Err(Reason::StepTooLarge);
+        }
+
+        if step < self.min_step {
+            return Err(Reason::StepTooSmall);
+        }
+
+        match order {
+            Order::Asc if level < prev_level => Err(Reason::DirectionChange),
+            Order::Desc if level > prev_level => Err(Reason::DirectionChange),
+            _ => Ok(()),
+        }
+    }
+
+    /// Finds the first problem of a report, acting as if the level at index `removed` did not
+    /// exist.
+    fn find_problem(&self, row: &[Level], removed: Option<usize>) -> Option<Problem> {
+        let mut order = Order::Unknown;
+        let mut levels = row
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != removed);
+        let (_, &first_level) = levels.next()?;
+        let mut prev_level = first_level;
+
+        for (index, &level) in levels {
+            if let Err(reason) = self.check_step(prev_level, level, order) {
+                return Some(Problem { index, reason });
             }
 
-            match order {
-                Order::Unknown => {
-                    if diff < 0 {
-                        order = Order::Desc;
-                    } else {
-                        order = Order::Asc
-                    }
-                }
-                Order::Asc => {
-                    if diff < 0 {
-                        return false;
-                    }
+            if order == Order::Unknown && level != prev_level {
+                order = if level < prev_level {
+                    Order::Desc
+                } else {
+                    Order::Asc
+                };
+            }
+
+            prev_level = level;
+        }
+
+        None
+    }
+
+    /// Finds the level whose removal makes an unsafe report safe, in a constant number of passes
+    /// over the report and without allocating. Returns `Some(None)` for safe reports.
+    ///
+    /// All levels before the first problem are fine, so only the two levels of the offending step
+    /// and the level before them, which decided the direction, are worth removing. The lowest index
+    /// that works is returned. Only valid for policies with strict monotonicity, where the first
+    /// step decides the direction.
+    fn find_single_removal(&self, row: &[Level]) -> Option<Option<usize>> {
+        let Some(problem) = self.find_problem(row, None) else {
+            return Some(None);
+        };
+        if self.max_removals == 0 {
+            return None;
+        }
+
+        (problem.index.saturating_sub(2)..=problem.index)
+            .find(|removed| self.find_problem(row, Some(*removed)).is_none())
+            .map(Some)
+    }
+
+    /// Finds the fewest levels to remove to make a report safe, or `None` if that takes more than
+    /// `max_removals` levels. Safe reports need no removals.
+    fn find_removals(&self, row: &[Level]) -> Option<Vec<usize>> {
+        if self.allows_single_removal_check() {
+            return self
+                .find_single_removal(row)
+                .map(|removed| removed.into_iter().collect());
+        }
+
+        [Order::Asc, Order::Desc]
+            .into_iter()
+            .filter_map(|order| self.find_removals_in_order(row, order))
+            .min_by_key(Vec::len)
+    }
+
+    /// Dynamic program over the levels kept in a report going in direction `order`.
+    ///
+    /// For every level it records the fewest removals needed before it if it is kept, together
+    /// with the kept level before it. Among equally good choices, later levels are kept so that
+    /// earlier ones are removed. Only the `max_removals + 1` levels before a level can be the
+    /// previous kept one, so this takes `O(n * max_removals)` time.
+    fn find_removals_in_order(&self, row: &[Level], order: Order) -> Option<Vec<usize>> {
+        let mut num_removals = vec![usize::MAX; row.len()];
+        let mut prev_kept = vec![None; row.len()];
+
+        for i in 0..row.len() {
+            if i <= self.max_removals {
+                num_removals[i] = i;
+            }
+
+            for j in i.saturating_sub(self.max_removals.saturating_add(1))..i {
+                if num_removals[j] == usize::MAX {
+                    continue;
                 }
-                Order::Desc => {
-                    if diff > 0 {
-                        return false;
-                    }
+
+                let candidate = num_removals[j] + (i - j - 1);
+                if candidate <= num_removals[i] && self.check_step(row[j], row[i], order).is_ok() {
+                    num_removals[i] = candidate;
+                    prev_kept[i] = Some(j);
                 }
             }
+        }
 
-            prev_level = level
+        let last_kept = (0..row.len())
+            .filter(|i| num_removals[*i] != usize::MAX)
+            .min_by_key(|i| (num_removals[*i] + (row.len() - 1 - i), Reverse(*i)));
+        let Some(last_kept) = last_kept else {
+            // only empty reports have no level to keep
+            return Some(Vec::new());
+        };
+
+        if num_removals[last_kept] + (row.len() - 1 - last_kept) > self.max_removals {
+            return None;
         }
 
-        true
-    } else {
-        true
+        let mut kept = vec![false; row.len()];
+        let mut i = Some(last_kept);
+        while let Some(kept_i) = i {
+            kept[kept_i] = true;
+            i = prev_kept[kept_i];
+        }
+
+        Some((0..row.len()).filter(|i| !kept[*i]).collect())
     }
 }
 
-fn get_input() -> Input {
-    let input = File::open("./src/resources/day_2.txt").expect("input file for day 2 not found");
-    let reader = BufReader::new(input);
+/// Why a report is unsafe.
+#[derive(Debug, Clone, Copy)]
+enum Reason {
+    ZeroStep,
+    StepTooSmall,
+    StepTooLarge,
+    DirectionChange,
+}
 
-    let mut rows = Vec::new();
+impl Display for Reason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reason::ZeroStep => write!(f, "zero step"),
+            Reason::StepTooSmall => write!(f, "step too small"),
+            Reason::StepTooLarge => write!(f, "step too large"),
+            Reason::DirectionChange => write!(f, "direction change"),
+        }
+    }
+}
 
-    for line in reader.lines() {
-        let line = line.expect("could not read next line");
+/// The first level of a report that breaks the safety rules, compared to the level before it.
+#[derive(Debug, Clone, Copy)]
+struct Problem {
+    index: usize,
+    reason: Reason,
+}
 
-        let parts = line.split_whitespace();
+#[derive(Debug, Clone)]
+struct Diagnosis {
+    /// `None` for safe reports.
+    problem: Option<Problem>,
+    /// The levels whose removal makes the report safe, if the policy allows removing that many.
+    removed_indices: Option<Vec<usize>>,
+}
 
-        let row: Vec<_> = parts
-            .map(|num| num.parse::<u64>().expect("could not parse to u64"))
-            .collect();
+fn diagnose(row: &[Level], policy: SafetyPolicy) -> Diagnosis {
+    Diagnosis {
+        problem: policy.find_problem(row, None),
+        removed_indices: policy.find_removals(row),
+    }
+}
 
-        rows.push(row);
+fn join_indices(indices: &[usize], separator: &str) -> String {
+    let indices: Vec<String> = indices.iter().map(usize::to_string).collect();
+    indices.join(separator)
+}
+
+fn print_diagnoses(input: &Input, policy: SafetyPolicy) {
+    for (i, row) in input.rows.iter().enumerate() {
+        let diagnosis = diagnose(row, policy);
+        let Some(problem) = diagnosis.problem else {
+            continue;
+        };
+
+        let dampened = match diagnosis.removed_indices {
+            Some(removed_indices) => format!(
+                "safe without the levels at indices {}",
+                join_indices(&removed_indices, ", ")
+            ),
+            None => format!("unsafe even with {} level(s) removed", policy.max_removals),
+        };
+        println!(
+            "Report {}: {} at index {}, {}",
+            i + 1,
+            problem.reason,
+            problem.index,
+            dampened
+        );
     }
+}
 
-    Input { rows }
+fn write_diagnoses_csv(input: &Input, policy: SafetyPolicy, path: PathBuf) {
+    let mut csv =
+        String::from("report,safe,offending_index,reason,safe_with_removals,removed_indices\n");
+
+    for (i, row) in input.rows.iter().enumerate() {
+        let diagnosis = diagnose(row, policy);
+        let optional = |value: Option<String>| value.unwrap_or_default();
+
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            i + 1,
+            diagnosis.problem.is_none(),
+            optional(diagnosis.problem.map(|problem| problem.index.to_string())),
+            optional(diagnosis.problem.map(|problem| problem.reason.to_string())),
+            diagnosis.removed_indices.is_some(),
+            optional(
+                diagnosis
+                    .removed_indices
+                    .map(|indices| join_indices(&indices, ";"))
+            ),
+        ));
+    }
+
+    fs::write(&path, csv).expect("could not write diagnoses for day 2");
+    println!("Diagnoses written to {}", path.display());
+}
+
+fn get_input(path: &Path) -> Result<Input, ParseError> {
+    let input = fs::read_to_string(path).expect("input file for day 2 not found");
+
+    parse_input(&input)
+}
+
+fn parse_input(input: &str) -> Result<Input, ParseError> {
+    let rows = parse::lines(input)
+        .map(|line| line.list::<Level>(Separator::Whitespace))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Input { rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{Location, ParseErrorKind};
+
+    #[test]
+    fn negative_levels() {
+        assert!(SafetyPolicy::STRICT.is_safe(&[-1, -3, -4, -7]));
+        assert!(SafetyPolicy::STRICT.is_safe(&[-2, 1, 3]));
+        assert!(!SafetyPolicy::STRICT.is_safe(&[-1, 3]));
+
+        assert!(!SafetyPolicy::STRICT.is_safe(&[-5, -3, -9, -1]));
+        assert!(SafetyPolicy::DAMPENED.is_safe(&[-5, -3, -9, -1]));
+        assert_eq!(
+            SafetyPolicy::DAMPENED.find_removals(&[-5, -3, -9, -1]),
+            Some(vec![2])
+        );
+    }
+
+    #[test]
+    fn extreme_levels() {
+        let min = i64::MIN as Level;
+        let max = i64::MAX as Level;
+        let unsigned_max = u64::MAX as Level;
+
+        assert!(SafetyPolicy::STRICT.is_safe(&[min, min + 1, min + 3]));
+        assert!(SafetyPolicy::STRICT.is_safe(&[max, max - 2, max - 5]));
+        assert!(SafetyPolicy::STRICT.is_safe(&[unsigned_max - 3, unsigned_max]));
+
+        assert!(!SafetyPolicy::STRICT.is_safe(&[min, max]));
+        assert!(!SafetyPolicy::DAMPENED.is_safe(&[min, max, min]));
+        assert!(!SafetyPolicy::DAMPENED.is_safe(&[min, unsigned_max, 0]));
+
+        assert_eq!(
+            SafetyPolicy::DAMPENED.find_removals(&[1, min, 2, 3]),
+            Some(vec![1])
+        );
+        assert_eq!(
+            SafetyPolicy::DAMPENED.find_removals(&[unsigned_max - 2, unsigned_max - 1, 0]),
+            Some(vec![2])
+        );
+    }
+
+    #[test]
+    fn many_removals() {
+        let policy = SafetyPolicy {
+            max_removals: usize::MAX,
+            ..SafetyPolicy::STRICT
+        };
+
+        assert_eq!(policy.find_removals(&[1, 9, 2, 9, 3]), Some(vec![1, 3]));
+    }
+
+    #[test]
+    fn parse_negative_levels() {
+        let input = parse_input("7 -5 -3\n-5 18446744073709551615\n").unwrap();
+
+        assert_eq!(
+            input.rows,
+            vec![vec![7, -5, -3], vec![-5, u64::MAX as Level]]
+        );
+    }
+
+    #[test]
+    fn parse_invalid_level() {
+        let error = parse_input("1 2\n3 -x 4\n").unwrap_err();
+
+        assert_eq!(error.location, Location { line: 2, column: 3 });
+        assert_eq!(error.kind, ParseErrorKind::InvalidValue("-x".to_string()));
+    }
 }
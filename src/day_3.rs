@@ -1,6 +1,10 @@
+use crate::cli::Args;
+use crate::fetch;
+use crate::output;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::iter::Peekable;
+use std::path::Path;
 use std::str::Chars;
 
 #[derive(Clone)]
@@ -8,8 +12,8 @@ struct Input {
     lines: Vec<String>,
 }
 
-pub fn solve() {
-    let input = get_input();
+pub fn solve(args: &Args) {
+    let input = get_input(&fetch::selected_input_path(args, 3));
 
     solve_puzzle_1(input.clone());
     solve_puzzle_2(input)
@@ -255,7 +259,7 @@ fn solve_puzzle_1(input: Input) {
         total_num += expression.evaluate();
     }
 
-    println!("Puzzle 1: Result of multiplications: {}", total_num);
+    output::print_answer(1, "Result of multiplications", total_num);
 }
 
 fn solve_puzzle_2(input: Input) {
@@ -271,11 +275,11 @@ fn solve_puzzle_2(input: Input) {
         total_num += expression.evaluate();
     }
 
-    println!("Puzzle 2: Result of enabled multiplications: {}", total_num);
+    output::print_answer(2, "Result of enabled multiplications", total_num);
 }
 
-fn get_input() -> Input {
-    let file = File::open("./src/resources/day_3.txt").expect("could not open file for day 3");
+fn get_input(path: &Path) -> Input {
+    let file = File::open(path).expect("could not open file for day 3");
     let buf_reader = BufReader::new(file);
 
     let lines = buf_reader
@@ -1,21 +1,51 @@
-use std::fs::File;
-use std::io::BufRead;
+use crate::cli::Args;
+use crate::fetch;
+use crate::output;
+use crate::parse::{self, ParseError};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
 
-const LEN_XMAS: usize = "XMAS".len();
+const XMAS: &str = "XMAS";
+const MAS: &str = "MAS";
 
 #[derive(Clone)]
 struct Input {
-    lines: Vec<String>,
+    text: String,
 }
 
-pub fn solve() {
-    let input = get_input();
+pub fn solve(args: &Args) {
+    let input = get_input(&fetch::selected_input_path(args, 4));
+
+    let shape = if args.flag("ragged") {
+        Shape::Ragged
+    } else {
+        Shape::Rectangular
+    };
+
+    let topology = if args.flag("wrap") {
+        Topology::Toroidal
+    } else {
+        Topology::Bounded
+    };
+
+    let matrix = match Matrix::new(input, shape) {
+        Ok(matrix) => matrix.with_topology(topology),
+        Err(error) => {
+            println!("Invalid input: {}", error);
+            return;
+        }
+    };
+
+    solve_puzzle_1(&matrix);
+    solve_puzzle_2(&matrix);
 
-    solve_puzzle_1(input.clone());
-    solve_puzzle_2(input);
+    if args.flag("render") {
+        render(&matrix, RenderOptions::from_args(args));
+    }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq)]
 struct Position {
     row: usize,
     column: usize,
@@ -23,211 +53,294 @@ struct Position {
 
 struct Matrix {
     raw_matrix: Vec<Vec<char>>,
+    len_columns: usize,
+    topology: Topology,
 }
 
-enum Direction {
-    None,
-    Forwards,
-    Backwards,
+/// Whether all rows of a matrix must have the same width.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Shape {
+    Rectangular,
+    /// Rows may differ in width. Cells missing from shorter rows are treated as empty.
+    Ragged,
 }
 
-impl Matrix {
-    fn new(input: Input) -> Matrix {
-        let raw_matrix = input
-            .lines
-            .into_iter()
-            .map(|line| line.chars().collect())
-            .collect();
+/// How words behave when they reach the edge of a matrix.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Topology {
+    Bounded,
+    /// Words continue on the opposite edge, so the right edge wraps to the left and the bottom
+    /// edge wraps to the top.
+    Toroidal,
+}
 
-        Matrix { raw_matrix }
-    }
+#[derive(Debug)]
+enum MatrixError {
+    Empty,
+    /// A row of a rectangular matrix is not as wide as the first one.
+    Parse(ParseError),
+}
 
-    fn num_xmas(&self, position: Position) -> u8 {
-        let mut num_xmas = 0;
-        let char_at_position = self
-            .raw_matrix
-            .get(position.row)
-            .and_then(|row| row.get(position.column))
-            .expect("illegal input");
-        if 'X' != *char_at_position {
-            return num_xmas;
+impl Display for MatrixError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatrixError::Empty => write!(f, "empty matrix not supported"),
+            MatrixError::Parse(error) => write!(f, "{}", error),
         }
+    }
+}
 
-        // search in all directions
+impl std::error::Error for MatrixError {}
 
-        // horizontal back
-        if self.is_xmas(position, Direction::None, Direction::Backwards) {
-            num_xmas += 1;
-        }
+impl From<ParseError> for MatrixError {
+    fn from(error: ParseError) -> Self {
+        MatrixError::Parse(error)
+    }
+}
 
-        // horizontal forward
-        if self.is_xmas(position, Direction::None, Direction::Forwards) {
-            num_xmas += 1;
-        }
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Direction {
+    None,
+    Forwards,
+    Backwards,
+}
 
-        // vertical back
-        if self.is_xmas(position, Direction::Backwards, Direction::None) {
-            num_xmas += 1;
+impl Direction {
+    fn sign(self) -> isize {
+        match self {
+            Direction::None => 0,
+            Direction::Forwards => 1,
+            Direction::Backwards => -1,
         }
+    }
+}
 
-        // vertical forward
-        if self.is_xmas(position, Direction::Forwards, Direction::None) {
-            num_xmas += 1;
-        }
+/// The direction a word is read in, e.g. backwards along the rows and forwards along the columns.
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct Heading {
+    row_direction: Direction,
+    column_direction: Direction,
+}
 
-        // diagonal 1 back
-        if self.is_xmas(position, Direction::Backwards, Direction::Backwards) {
-            num_xmas += 1;
+impl Heading {
+    const fn new(row_direction: Direction, column_direction: Direction) -> Heading {
+        Heading {
+            row_direction,
+            column_direction,
         }
+    }
 
-        // diagonal 1 forward
-        if self.is_xmas(position, Direction::Forwards, Direction::Forwards) {
-            num_xmas += 1;
+    fn ansi_color(self) -> u8 {
+        match (self.row_direction, self.column_direction) {
+            (Direction::None, Direction::Backwards) => 31,
+            (Direction::None, Direction::Forwards) => 32,
+            (Direction::Backwards, Direction::None) => 33,
+            (Direction::Forwards, Direction::None) => 34,
+            (Direction::Backwards, Direction::Backwards) => 35,
+            (Direction::Forwards, Direction::Forwards) => 36,
+            (Direction::Backwards, Direction::Forwards) => 91,
+            (Direction::Forwards, Direction::Backwards) => 92,
+            (Direction::None, Direction::None) => 39,
         }
+    }
+}
 
-        // diagonal 2 back
-        if self.is_xmas(position, Direction::Backwards, Direction::Forwards) {
-            num_xmas += 1;
-        }
+const HEADINGS: [Heading; 8] = [
+    // horizontal back
+    Heading::new(Direction::None, Direction::Backwards),
+    // horizontal forward
+    Heading::new(Direction::None, Direction::Forwards),
+    // vertical back
+    Heading::new(Direction::Backwards, Direction::None),
+    // vertical forward
+    Heading::new(Direction::Forwards, Direction::None),
+    // diagonal 1 back
+    Heading::new(Direction::Backwards, Direction::Backwards),
+    // diagonal 1 forward
+    Heading::new(Direction::Forwards, Direction::Forwards),
+    // diagonal 2 back
+    Heading::new(Direction::Backwards, Direction::Forwards),
+    // diagonal 2 forward
+    Heading::new(Direction::Forwards, Direction::Backwards),
+];
+
+/// A word found in the matrix together with the positions of its letters.
+struct Word {
+    heading: Heading,
+    positions: Vec<Position>,
+}
 
-        // diagonal 2 forwards
-        if self.is_xmas(position, Direction::Forwards, Direction::Backwards) {
-            num_xmas += 1;
-        }
+impl Matrix {
+    fn new(input: Input, shape: Shape) -> Result<Matrix, MatrixError> {
+        let raw_matrix: Vec<Vec<char>> = match shape {
+            Shape::Rectangular => parse::char_grid(&input.text)?,
+            Shape::Ragged => parse::lines(&input.text)
+                .map(|line| line.text.chars().collect())
+                .collect(),
+        };
 
-        num_xmas
-    }
+        let first_row = raw_matrix.first().ok_or(MatrixError::Empty)?;
 
-    fn is_xmas(
-        &self,
-        position: Position,
-        row_direction: Direction,
-        column_direction: Direction,
-    ) -> bool {
-        let row_check = match row_direction {
-            Direction::Forwards => self.len_rows() - position.row >= LEN_XMAS,
-            Direction::Backwards => position.row + 1 >= LEN_XMAS,
-            Direction::None => true,
+        let len_columns = match shape {
+            Shape::Rectangular => first_row.len(),
+            Shape::Ragged => raw_matrix.iter().map(Vec::len).max().unwrap_or_default(),
         };
 
-        let column_check = match column_direction {
-            Direction::Forwards => self.len_columns() - position.column >= LEN_XMAS,
-            Direction::Backwards => position.column + 1 >= LEN_XMAS,
-            Direction::None => true,
-        };
+        Ok(Matrix {
+            raw_matrix,
+            len_columns,
+            topology: Topology::Bounded,
+        })
+    }
 
-        if row_check && column_check {
-            let row_operation: Box<dyn Fn(usize, usize) -> usize> = match row_direction {
-                Direction::None => Box::new(|index, _| index),
-                Direction::Forwards => Box::new(|index, num| index + num),
-                Direction::Backwards => Box::new(|index, num| index - num),
-            };
+    fn with_topology(mut self, topology: Topology) -> Matrix {
+        self.topology = topology;
+        self
+    }
 
-            let column_operation: Box<dyn Fn(usize, usize) -> usize> = match column_direction {
-                Direction::None => Box::new(|index, _| index),
-                Direction::Forwards => Box::new(|index, num| index + num),
-                Direction::Backwards => Box::new(|index, num| index - num),
-            };
+    fn num_xmas(&self, position: Position) -> u8 {
+        self.xmas_words(position).len() as u8
+    }
+
+    fn xmas_words(&self, position: Position) -> Vec<Word> {
+        if Some('X') != self.get(position) {
+            return Vec::new();
+        }
 
-            let second = self
-                .raw_matrix
-                .get(row_operation(position.row, 1))
-                .and_then(|row| row.get(column_operation(position.column, 1)))
-                .unwrap_or_else(|| unreachable!());
-            if 'M' == *second {
-                let third = self
-                    .raw_matrix
-                    .get(row_operation(position.row, 2))
-                    .and_then(|row| row.get(column_operation(position.column, 2)))
-                    .unwrap_or_else(|| unreachable!());
-
-                if 'A' == *third {
-                    let fourth = self
-                        .raw_matrix
-                        .get(row_operation(position.row, 3))
-                        .and_then(|row| row.get(column_operation(position.column, 3)))
-                        .unwrap_or_else(|| unreachable!());
-
-                    if 'S' == *fourth {
-                        return true;
-                    }
+        let mut words: Vec<Word> = Vec::new();
+
+        // search in all directions
+        for heading in HEADINGS {
+            if let Some(word) = self.find_word(XMAS, position, heading, 0) {
+                // in a wrapping matrix smaller than the word, different headings can visit the
+                // same cells in the same order
+                if words.iter().all(|found| found.positions != word.positions) {
+                    words.push(word);
                 }
             }
         }
 
-        false
+        words
     }
 
     fn is_cross_mas(&self, position: Position) -> bool {
-        let char_at_position = self
-            .raw_matrix
-            .get(position.row)
-            .and_then(|row| row.get(position.column))
-            .expect("illegal input");
-        if 'A' != *char_at_position {
-            return false;
+        self.cross_mas_words(position).is_some()
+    }
+
+    fn cross_mas_words(&self, position: Position) -> Option<[Word; 2]> {
+        if Some('A') != self.get(position) {
+            return None;
         }
 
-        let is_mas_diagonal_1 = self.is_mas(position, Direction::Forwards, Direction::Forwards)
-            || self.is_mas(position, Direction::Backwards, Direction::Backwards);
-        let is_mas_diagonal_2 = self.is_mas(position, Direction::Backwards, Direction::Forwards)
-            || self.is_mas(position, Direction::Forwards, Direction::Backwards);
+        let diagonal_1 = self
+            .find_word(MAS, position, HEADINGS[4], 1)
+            .or_else(|| self.find_word(MAS, position, HEADINGS[5], 1))?;
+        let diagonal_2 = self
+            .find_word(MAS, position, HEADINGS[6], 1)
+            .or_else(|| self.find_word(MAS, position, HEADINGS[7], 1))?;
+
+        // in a wrapping matrix less than three columns wide both diagonals can coincide
+        if diagonal_1
+            .positions
+            .iter()
+            .all(|p| diagonal_2.positions.contains(p))
+        {
+            return None;
+        }
 
-        is_mas_diagonal_1 && is_mas_diagonal_2
+        Some([diagonal_1, diagonal_2])
     }
 
-    fn is_mas(
+    /// Looks for `word` read along `heading`, with its letter at index `anchor` placed at `position`.
+    ///
+    /// A word never uses the same cell twice, even if it could wrap around onto itself.
+    fn find_word(
         &self,
+        word: &str,
         position: Position,
-        row_direction: Direction,
-        column_direction: Direction,
-    ) -> bool {
-        let row_check = match row_direction {
-            Direction::Forwards => position.row >= 1 && position.row < self.len_rows() - 1,
-            Direction::Backwards => position.row >= 1 && position.row < self.len_rows() - 1,
-            Direction::None => true,
-        };
-
-        let column_check = match column_direction {
-            Direction::Forwards => position.column >= 1 && position.column < self.len_columns() - 1,
-            Direction::Backwards => {
-                position.column >= 1 && position.column < self.len_columns() - 1
+        heading: Heading,
+        anchor: usize,
+    ) -> Option<Word> {
+        let mut positions = Vec::with_capacity(word.len());
+
+        for (i, expected) in word.chars().enumerate() {
+            let next_position = self.offset(position, heading, i as isize - anchor as isize)?;
+            if Some(expected) != self.get(next_position) || positions.contains(&next_position) {
+                return None;
             }
-            Direction::None => true,
-        };
 
-        if row_check && column_check {
-            let (row_index_1, row_index_2) = match row_direction {
-                Direction::Forwards => (position.row + 1, position.row - 1),
-                Direction::Backwards => (position.row - 1, position.row + 1),
-                Direction::None => unreachable!(),
-            };
+            positions.push(next_position);
+        }
 
-            let (column_index_1, column_index_2) = match column_direction {
-                Direction::Forwards => (position.column + 1, position.column - 1),
-                Direction::Backwards => (position.column - 1, position.column + 1),
-                Direction::None => unreachable!(),
-            };
+        Some(Word { heading, positions })
+    }
+
+    fn offset(&self, position: Position, heading: Heading, distance: isize) -> Option<Position> {
+        if self.topology == Topology::Toroidal {
+            let row = (position.row as isize + heading.row_direction.sign() * distance)
+                .rem_euclid(self.len_rows() as isize);
+            let column = (position.column as isize + heading.column_direction.sign() * distance)
+                .rem_euclid(self.len_columns() as isize);
+
+            return Some(Position {
+                row: row as usize,
+                column: column as usize,
+            });
+        }
+
+        let row = position
+            .row
+            .checked_add_signed(heading.row_direction.sign() * distance)?;
+        let column = position
+            .column
+            .checked_add_signed(heading.column_direction.sign() * distance)?;
+
+        if row < self.len_rows() && column < self.len_columns() {
+            Some(Position { row, column })
+        } else {
+            None
+        }
+    }
+
+    fn get(&self, position: Position) -> Option<char> {
+        self.raw_matrix
+            .get(position.row)
+            .and_then(|row| row.get(position.column))
+            .copied()
+    }
 
-            let first = self
-                .raw_matrix
-                .get(row_index_1)
-                .and_then(|row| row.get(column_index_1))
-                .unwrap_or_else(|| unreachable!());
-            if 'M' == *first {
-                let third = self
-                    .raw_matrix
-                    .get(row_index_2)
-                    .and_then(|row| row.get(column_index_2))
-                    .unwrap_or_else(|| unreachable!());
-
-                if 'S' == *third {
-                    return true;
+    /// Renders the matrix with every letter that is not part of one of `words` replaced by `.`.
+    fn render(&self, words: &[Word], color: bool) -> String {
+        let mut headings: Vec<Vec<Option<Heading>>> = self
+            .raw_matrix
+            .iter()
+            .map(|row| vec![None; row.len()])
+            .collect();
+
+        for word in words {
+            for position in &word.positions {
+                headings[position.row][position.column].get_or_insert(word.heading);
+            }
+        }
+
+        let mut rendering = String::new();
+
+        for (row, row_headings) in self.raw_matrix.iter().zip(&headings) {
+            for (char, heading) in row.iter().zip(row_headings) {
+                match heading {
+                    Some(heading) if color => rendering.push_str(&format!(
+                        "\x1b[{}m{}\x1b[0m",
+                        heading.ansi_color(),
+                        char
+                    )),
+                    Some(_) => rendering.push(*char),
+                    None => rendering.push('.'),
                 }
             }
+            rendering.push('\n');
         }
 
-        false
+        rendering
     }
 
     fn len_rows(&self) -> usize {
@@ -235,16 +348,19 @@ impl Matrix {
     }
 
     fn len_columns(&self) -> usize {
-        self.raw_matrix
-            .first()
-            .expect("empty matrix not supported")
-            .len()
+        self.len_columns
     }
 }
 
-fn solve_puzzle_1(input: Input) {
-    let matrix = Matrix::new(input);
+fn solve_puzzle_1(matrix: &Matrix) {
+    output::print_answer(1, "Number of 'XMAS' found", count_xmas(matrix));
+}
+
+fn solve_puzzle_2(matrix: &Matrix) {
+    output::print_answer(2, "Number of cross 'MAS' found", count_cross_mas(matrix));
+}
 
+fn count_xmas(matrix: &Matrix) -> u32 {
     let mut num_xmas: u32 = 0;
 
     for row_i in 0..matrix.len_rows() {
@@ -255,12 +371,11 @@ fn solve_puzzle_1(input: Input) {
             }) as u32;
         }
     }
-    println!("Puzzle 1: Number of 'XMAS' found: {}", num_xmas);
-}
 
-fn solve_puzzle_2(input: Input) {
-    let matrix = Matrix::new(input);
+    num_xmas
+}
 
+fn count_cross_mas(matrix: &Matrix) -> u32 {
     let mut num_cross_mas = 0;
 
     for row_i in 0..matrix.len_rows() {
@@ -273,17 +388,202 @@ fn solve_puzzle_2(input: Input) {
             }
         }
     }
-    println!("Puzzle 2: Number of cross 'MAS' found: {}", num_cross_mas);
+
+    num_cross_mas
+}
+
+struct RenderOptions {
+    color: bool,
+    output: Option<PathBuf>,
+}
+
+impl RenderOptions {
+    fn from_args(args: &Args) -> RenderOptions {
+        RenderOptions {
+            color: args.flag("color"),
+            output: args.value("render-output"),
+        }
+    }
 }
 
-fn get_input() -> Input {
-    let file = File::open("./src/resources/day_4.txt").expect("could not open file for day 4");
-    let buf_reader = std::io::BufReader::new(file);
+fn render(matrix: &Matrix, options: RenderOptions) {
+    let mut xmas_words = Vec::new();
+    let mut cross_mas_words = Vec::new();
+
+    for row_i in 0..matrix.len_rows() {
+        for column_i in 0..matrix.len_columns() {
+            let position = Position {
+                row: row_i,
+                column: column_i,
+            };
+
+            xmas_words.extend(matrix.xmas_words(position));
+            cross_mas_words.extend(matrix.cross_mas_words(position).into_iter().flatten());
+        }
+    }
+
+    let rendering = format!(
+        "'XMAS' matches:\n{}\nCross 'MAS' matches:\n{}",
+        matrix.render(&xmas_words, options.color),
+        matrix.render(&cross_mas_words, options.color)
+    );
+
+    match options.output {
+        Some(path) => {
+            fs::write(&path, rendering).expect("could not write rendering for day 4");
+            println!("Rendering written to {}", path.display());
+        }
+        None => print!("{}", rendering),
+    }
+}
+
+fn get_input(path: &Path) -> Input {
+    let text = fs::read_to_string(path).expect("could not open file for day 4");
+
+    Input { text }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{Location, ParseErrorKind};
+
+    fn matrix(text: &str, shape: Shape) -> Matrix {
+        Matrix::new(
+            Input {
+                text: text.to_string(),
+            },
+            shape,
+        )
+        .unwrap()
+    }
+
+    fn positions(matrix: &Matrix) -> impl Iterator<Item = Position> + '_ {
+        (0..matrix.len_rows()).flat_map(move |row| {
+            (0..matrix.len_columns()).map(move |column| Position { row, column })
+        })
+    }
+
+    #[test]
+    fn render_xmas() {
+        let matrix = matrix("XMASX\nSAMXM\n", Shape::Rectangular);
+        let words: Vec<Word> = positions(&matrix)
+            .flat_map(|position| matrix.xmas_words(position))
+            .collect();
+
+        assert_eq!(words.len(), 2);
+        assert_eq!(matrix.render(&words, false), "XMAS.\nSAMX.\n");
+    }
+
+    #[test]
+    fn render_cross_mas() {
+        let matrix = matrix("MXS\nXAX\nMXS\n", Shape::Rectangular);
+        let words: Vec<Word> = positions(&matrix)
+            .flat_map(|position| matrix.cross_mas_words(position).into_iter().flatten())
+            .collect();
+
+        assert_eq!(words.len(), 2);
+        assert_eq!(matrix.render(&words, false), "M.S\n.A.\nM.S\n");
+    }
+
+    #[test]
+    fn render_nothing() {
+        let matrix = matrix("XMAS\n", Shape::Rectangular);
+
+        assert_eq!(matrix.render(&[], false), "....\n");
+    }
+
+    #[test]
+    fn short_row() {
+        let input = Input {
+            text: "XMAS\nXMA\nXMAS\n".to_string(),
+        };
+
+        let Err(MatrixError::Parse(error)) = Matrix::new(input, Shape::Rectangular) else {
+            panic!("a short row must be rejected");
+        };
+        assert_eq!(error.location, Location { line: 2, column: 4 });
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::RowWidth {
+                width: 3,
+                expected_width: 4
+            }
+        );
+    }
+
+    #[test]
+    fn empty_matrix() {
+        let input = Input {
+            text: String::new(),
+        };
+
+        assert!(matches!(
+            Matrix::new(input, Shape::Rectangular),
+            Err(MatrixError::Empty)
+        ));
+    }
+
+    #[test]
+    fn ragged_counting() {
+        let matrix = matrix("XMAS\nM\nASAMX\nS.S\n", Shape::Ragged);
 
-    let lines = buf_reader
-        .lines()
-        .map(|line| line.expect("could not read line"))
-        .collect();
+        assert_eq!(matrix.len_columns(), 5);
+        assert_eq!(count_xmas(&matrix), 3);
+        assert_eq!(count_cross_mas(&matrix), 0);
+    }
+
+    #[test]
+    fn ragged_cells_are_empty() {
+        let matrix = matrix("M.S\nA\nM.S\n", Shape::Ragged);
 
-    Input { lines }
+        assert_eq!(matrix.get(Position { row: 1, column: 1 }), None);
+        assert_eq!(count_cross_mas(&matrix), 0);
+    }
+
+    const EXAMPLE: &str = "\
+MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX
+";
+
+    #[test]
+    fn example() {
+        let matrix = matrix(EXAMPLE, Shape::Rectangular);
+
+        assert_eq!(count_xmas(&matrix), 18);
+        assert_eq!(count_cross_mas(&matrix), 9);
+    }
+
+    #[test]
+    fn wrap_around_a_row() {
+        let bounded = matrix("MASX\n", Shape::Rectangular);
+        let toroidal = matrix("MASX\n", Shape::Rectangular).with_topology(Topology::Toroidal);
+
+        assert_eq!(count_xmas(&bounded), 0);
+        assert_eq!(count_xmas(&toroidal), 1);
+    }
+
+    #[test]
+    fn wrap_around_a_grid_smaller_than_the_word() {
+        let matrix = matrix("XM\nSA\n", Shape::Rectangular).with_topology(Topology::Toroidal);
+
+        assert_eq!(count_xmas(&matrix), 0);
+        assert_eq!(count_cross_mas(&matrix), 0);
+    }
+
+    #[test]
+    fn wrap_around_the_example() {
+        let matrix = matrix(EXAMPLE, Shape::Rectangular).with_topology(Topology::Toroidal);
+
+        assert!(count_xmas(&matrix) >= 18);
+        assert!(count_cross_mas(&matrix) >= 9);
+    }
 }
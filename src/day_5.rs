@@ -1,6 +1,13 @@
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::cli::Args;
+use crate::fetch;
+use crate::output;
+use crate::parse::{self, Line, ParseError, Separator};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 #[derive(Clone, Debug)]
 struct Page {
@@ -20,9 +27,115 @@ impl Page {
 #[derive(Clone, Debug)]
 struct Graph {
     nodes: HashMap<PageNumber, Page>,
+    /// Compacted ids of the pages, used to index `successors`.
+    ids: HashMap<PageNumber, usize>,
+    /// Pages that directly depend on each page, by compacted id.
+    successors: Vec<BitSet>,
 }
 
-impl Graph {}
+/// A set of compacted page ids.
+#[derive(Clone, Debug, Default)]
+struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    fn insert(&mut self, id: usize) {
+        let word = id / 64;
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (id % 64);
+    }
+
+    fn remove(&mut self, id: usize) {
+        if let Some(word) = self.words.get_mut(id / 64) {
+            *word &= !(1 << (id % 64));
+        }
+    }
+
+    fn contains(&self, id: usize) -> bool {
+        self.words
+            .get(id / 64)
+            .is_some_and(|word| word & (1 << (id % 64)) != 0)
+    }
+
+    fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The smallest id in the set.
+    fn first(&self) -> Option<usize> {
+        self.words
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != 0)
+            .map(|(i, word)| i * 64 + word.trailing_zeros() as usize)
+    }
+
+    fn intersects(&self, other: &BitSet) -> bool {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .any(|(word, other_word)| word & other_word != 0)
+    }
+
+    fn intersection(&self, other: &BitSet) -> BitSet {
+        let words = self
+            .words
+            .iter()
+            .zip(&other.words)
+            .map(|(word, other_word)| word & other_word)
+            .collect();
+
+        BitSet { words }
+    }
+
+    fn is_subset(&self, other: &BitSet) -> bool {
+        self.words
+            .iter()
+            .enumerate()
+            .all(|(i, word)| word & !other.words.get(i).copied().unwrap_or(0) == 0)
+    }
+
+    fn difference(&self, other: &BitSet) -> BitSet {
+        let words = self
+            .words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| word & !other.words.get(i).copied().unwrap_or(0))
+            .collect();
+
+        BitSet { words }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| i * 64 + bit)
+        })
+    }
+}
+
+/// Which pages of an update can be reached from each other following the rules between them.
+struct Reachability<'a> {
+    graph: &'a Graph,
+    reachable: HashMap<PageNumber, BitSet>,
+}
+
+impl Reachability<'_> {
+    /// Whether the rules require `source` to come before `target`.
+    fn reaches(&self, source: PageNumber, target: PageNumber) -> bool {
+        match (self.reachable.get(&source), self.graph.ids.get(&target)) {
+            (Some(reachable_pages), Some(id)) => reachable_pages.contains(*id),
+            _ => false,
+        }
+    }
+}
 
 impl Graph {
     fn new(orderings: Vec<Ordering>) -> Graph {
@@ -40,76 +153,741 @@ impl Graph {
                 .or_insert(Page::new(ordering.right, HashSet::new()));
         }
 
-        Graph { nodes }
+        let ids: HashMap<PageNumber, usize> = nodes
+            .keys()
+            .enumerate()
+            .map(|(id, page_number)| (*page_number, id))
+            .collect();
+
+        let mut successors = vec![BitSet::default(); ids.len()];
+        for page in nodes.values() {
+            for dependent_page in &page.dependent_pages {
+                successors[ids[&page.id]].insert(ids[dependent_page]);
+            }
+        }
+
+        Graph {
+            nodes,
+            ids,
+            successors,
+        }
     }
 
-    fn path_exists(&self, source: PageNumber, target: PageNumber, path: &[PageNumber]) -> bool {
-        let page = if let Some(page) = self.nodes.get(&source) {
-            page
-        } else {
+    /// Adds a rule, returning whether it was new.
+    ///
+    /// Reachability is always derived from the rules when needed, so only the direct successors
+    /// of the left page change.
+    fn add_rule(&mut self, ordering: &Ordering) -> bool {
+        let left_id = self.add_page(ordering.left);
+        let right_id = self.add_page(ordering.right);
+
+        self.successors[left_id].insert(right_id);
+        self.nodes
+            .get_mut(&ordering.left)
+            .unwrap()
+            .dependent_pages
+            .insert(ordering.right)
+    }
+
+    /// Removes a rule, returning whether it existed. The pages stay in the graph.
+    fn remove_rule(&mut self, ordering: &Ordering) -> bool {
+        let Some(page) = self.nodes.get_mut(&ordering.left) else {
             return false;
         };
 
-        let mut visited = HashSet::new();
-        let mut queue = VecDeque::new();
-        queue.push_back(page.clone());
+        if !page.dependent_pages.remove(&ordering.right) {
+            return false;
+        }
 
-        while let Some(page) = queue.pop_front() {
-            if page.id == target {
-                return true;
-            }
+        self.successors[self.ids[&ordering.left]].remove(self.ids[&ordering.right]);
+
+        true
+    }
+
+    fn add_page(&mut self, page_number: PageNumber) -> usize {
+        if let Some(id) = self.ids.get(&page_number) {
+            return *id;
+        }
 
-            visited.insert(page.id);
+        let id = self.successors.len();
+        self.nodes
+            .insert(page_number, Page::new(page_number, HashSet::new()));
+        self.ids.insert(page_number, id);
+        self.successors.push(BitSet::default());
+
+        id
+    }
+
+    /// Reachability between the pages of an update, only following rules between these pages.
+    fn reachability(&self, page_numbers: &[PageNumber]) -> Reachability<'_> {
+        let mut pages = BitSet::default();
+        for id in page_numbers
+            .iter()
+            .filter_map(|page_number| self.ids.get(page_number))
+        {
+            pages.insert(*id);
+        }
 
-            for prerequisite_page in page.dependent_pages {
-                if !visited.contains(&prerequisite_page) && path.contains(&prerequisite_page) {
-                    if let Some(page) = self.nodes.get(&prerequisite_page).cloned() {
-                        queue.push_back(page.clone())
+        let mut reachable = HashMap::with_capacity(page_numbers.len());
+
+        for page_number in page_numbers {
+            let mut reachable_pages = BitSet::default();
+
+            if let Some(&id) = self.ids.get(page_number) {
+                let mut stack = vec![id];
+                while let Some(id) = stack.pop() {
+                    for next_id in self.successors[id].intersection(&pages).iter() {
+                        if !reachable_pages.contains(next_id) {
+                            reachable_pages.insert(next_id);
+                            stack.push(next_id);
+                        }
                     }
                 }
             }
+
+            reachable.insert(*page_number, reachable_pages);
         }
 
-        false
+        Reachability {
+            graph: self,
+            reachable,
+        }
     }
 
     fn is_valid_ordering(&self, page_numbers: &[PageNumber]) -> bool {
-        for i in (1..page_numbers.len()).rev() {
-            let page_number = page_numbers[i];
-            for target_page_number in page_numbers[..i].iter().rev() {
-                if self.path_exists(page_number, *target_page_number, &page_numbers[..i]) {
-                    return false;
-                }
+        let reachability = self.reachability(page_numbers);
+        let mut seen_pages = BitSet::default();
+
+        for page_number in page_numbers {
+            if reachability.reachable[page_number].intersects(&seen_pages) {
+                return false;
+            }
+
+            if let Some(&id) = self.ids.get(page_number) {
+                seen_pages.insert(id);
             }
         }
 
         true
     }
 
-    fn order(&self, mut page_numbers: Vec<PageNumber>) -> Vec<PageNumber> {
-        'check_loop: loop {
-            for i in (1..page_numbers.len()).rev() {
-                let page_number = page_numbers[i];
-                for target_i in (0..i).rev() {
-                    let target_page_number = page_numbers[target_i];
-                    if self.path_exists(page_number, target_page_number, &page_numbers[..i]) {
-                        page_numbers.swap(i, target_i);
-                        continue 'check_loop;
+    /// Shortest chain of rules leading from `source` to `target` that only passes through `pages`.
+    fn find_path(
+        &self,
+        source: PageNumber,
+        target: PageNumber,
+        pages: &HashSet<PageNumber>,
+    ) -> Option<Vec<PageNumber>> {
+        let mut previous_pages = HashMap::new();
+        let mut queue = VecDeque::from([source]);
+
+        while let Some(page_number) = queue.pop_front() {
+            if page_number == target {
+                let mut path = vec![target];
+                while let Some(previous_page) = previous_pages.get(path.last().unwrap()) {
+                    path.push(*previous_page);
+                }
+                path.reverse();
+
+                return Some(path);
+            }
+
+            let Some(page) = self.nodes.get(&page_number) else {
+                continue;
+            };
+
+            let mut dependent_pages: Vec<PageNumber> = page
+                .dependent_pages
+                .iter()
+                .copied()
+                .filter(|dependent_page| pages.contains(dependent_page))
+                .collect();
+            dependent_pages.sort();
+
+            for dependent_page in dependent_pages {
+                if dependent_page != source && !previous_pages.contains_key(&dependent_page) {
+                    previous_pages.insert(dependent_page, page_number);
+                    queue.push_back(dependent_page);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Every pair of pages in the update whose order is forbidden by the rules between its pages.
+    fn violations(&self, page_numbers: &[PageNumber]) -> Vec<Violation> {
+        let pages: HashSet<PageNumber> = page_numbers.iter().copied().collect();
+        let reachability = self.reachability(page_numbers);
+        let mut violations = Vec::new();
+
+        for (earlier_position, earlier) in page_numbers.iter().enumerate() {
+            for (later_position, later) in
+                page_numbers.iter().enumerate().skip(earlier_position + 1)
+            {
+                if !reachability.reaches(*later, *earlier) {
+                    continue;
+                }
+
+                if let Some(path) = self.find_path(*later, *earlier, &pages) {
+                    violations.push(Violation {
+                        earlier: *earlier,
+                        earlier_position,
+                        later: *later,
+                        later_position,
+                        path,
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+
+    /// Orders the pages of an update with a topological sort over the rules between its pages.
+    ///
+    /// Whenever several pages could come next, the one appearing first in `page_numbers` wins.
+    /// Fails if the rules between the pages of the update contain a cycle.
+    fn order(&self, page_numbers: Vec<PageNumber>) -> Result<Vec<PageNumber>, CycleError> {
+        self.order_with(&page_numbers, &[])
+    }
+
+    /// Like [`Graph::order`], but additionally requires the page at the first index of each of
+    /// `extra_rules` to come before the page at the second index.
+    fn order_with(
+        &self,
+        page_numbers: &[PageNumber],
+        extra_rules: &[(usize, usize)],
+    ) -> Result<Vec<PageNumber>, CycleError> {
+        let positions: HashMap<PageNumber, usize> = page_numbers
+            .iter()
+            .enumerate()
+            .map(|(i, page_number)| (*page_number, i))
+            .collect();
+
+        let mut dependents = vec![Vec::new(); page_numbers.len()];
+        let mut prerequisites = vec![Vec::new(); page_numbers.len()];
+        let mut num_prerequisites = vec![0; page_numbers.len()];
+
+        for (i, page_number) in page_numbers.iter().enumerate() {
+            if let Some(page) = self.nodes.get(page_number) {
+                for dependent_page in &page.dependent_pages {
+                    if let Some(&dependent_i) = positions.get(dependent_page) {
+                        dependents[i].push(dependent_i);
+                        prerequisites[dependent_i].push(i);
+                        num_prerequisites[dependent_i] += 1;
                     }
                 }
             }
+        }
 
-            break 'check_loop;
+        for &(i, dependent_i) in extra_rules {
+            dependents[i].push(dependent_i);
+            prerequisites[dependent_i].push(i);
+            num_prerequisites[dependent_i] += 1;
         }
 
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..page_numbers.len())
+            .filter(|i| num_prerequisites[*i] == 0)
+            .map(Reverse)
+            .collect();
+        let mut ordered_page_numbers = Vec::with_capacity(page_numbers.len());
+
+        while let Some(Reverse(i)) = ready.pop() {
+            ordered_page_numbers.push(page_numbers[i]);
+
+            for &dependent_i in &dependents[i] {
+                num_prerequisites[dependent_i] -= 1;
+                if num_prerequisites[dependent_i] == 0 {
+                    ready.push(Reverse(dependent_i));
+                }
+            }
+        }
+
+        if ordered_page_numbers.len() < page_numbers.len() {
+            let cycle = Self::find_cycle(&prerequisites, &num_prerequisites)
+                .into_iter()
+                .map(|i| page_numbers[i])
+                .collect();
+
+            return Err(CycleError { cycle });
+        }
+
+        Ok(ordered_page_numbers)
+    }
+
+    /// Whether the rules between the pages of an update allow exactly one order.
+    ///
+    /// That is the case if every page of the sorted update has to come before the next one.
+    fn has_unique_order(&self, page_numbers: &[PageNumber]) -> Result<bool, CycleError> {
+        let ordered_page_numbers = self.order(page_numbers.to_vec())?;
+        let reachability = self.reachability(page_numbers);
+
+        Ok(ordered_page_numbers
+            .windows(2)
+            .all(|window| reachability.reaches(window[0], window[1])))
+    }
+
+    /// Counts the orders of an update that respect the rules between its pages, or `None` if the
+    /// update has more than [`MAX_COUNTED_PAGES`] pages.
+    fn count_orders(&self, page_numbers: &[PageNumber]) -> Option<u64> {
+        if page_numbers.len() > MAX_COUNTED_PAGES {
+            return None;
+        }
+
+        let prerequisites: Vec<usize> = self
+            .prerequisite_sets(page_numbers)
+            .iter()
+            .map(|prerequisites| prerequisites.iter().fold(0, |mask, i| mask | (1 << i)))
+            .collect();
+
+        // number of orders for each set of pages placed at the front of the update
+        let mut num_orders = vec![0_u64; 1 << page_numbers.len()];
+        num_orders[0] = 1;
+
+        for placed in 0..num_orders.len() {
+            if num_orders[placed] == 0 {
+                continue;
+            }
+
+            for (i, prerequisites) in prerequisites.iter().enumerate() {
+                if placed & (1 << i) == 0 && prerequisites & !placed == 0 {
+                    num_orders[placed | (1 << i)] += num_orders[placed];
+                }
+            }
+        }
+
+        num_orders.last().copied()
+    }
+
+    /// Lazily enumerates the orders of an update that respect the rules between its pages.
+    fn orders(&self, page_numbers: &[PageNumber]) -> Orders {
+        Orders {
+            page_numbers: page_numbers.to_vec(),
+            prerequisites: self.prerequisite_sets(page_numbers),
+            prefix: Vec::with_capacity(page_numbers.len()),
+            placed: BitSet::default(),
+            next_candidates: vec![0; page_numbers.len() + 1],
+            done: false,
+        }
+    }
+
+    /// The pages that are in the middle of at least one valid order of an update.
+    ///
+    /// A page fits at a position if all pages that have to come before it fit in front of it and
+    /// all pages that have to come after it fit behind it.
+    fn possible_middle_pages(&self, page_numbers: &[PageNumber]) -> Vec<PageNumber> {
+        let reachability = self.reachability(page_numbers);
+        let middle = page_numbers.len() / 2;
+
         page_numbers
+            .iter()
+            .copied()
+            .filter(|page_number| {
+                let num_before = page_numbers
+                    .iter()
+                    .filter(|other| reachability.reaches(**other, *page_number))
+                    .count();
+                let num_after = page_numbers
+                    .iter()
+                    .filter(|other| reachability.reaches(*page_number, **other))
+                    .count();
+
+                num_before <= middle && middle + num_after < page_numbers.len()
+            })
+            .collect()
+    }
+
+    /// For each page of an update, the positions of the pages that have to come before it.
+    fn prerequisite_sets(&self, page_numbers: &[PageNumber]) -> Vec<BitSet> {
+        let reachability = self.reachability(page_numbers);
+
+        page_numbers
+            .iter()
+            .map(|page_number| {
+                page_numbers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, other)| reachability.reaches(**other, *page_number))
+                    .fold(BitSet::default(), |mut prerequisites, (i, _)| {
+                        prerequisites.insert(i);
+                        prerequisites
+                    })
+            })
+            .collect()
+    }
+
+    /// Makes an update valid by moving as few pages as possible.
+    ///
+    /// The pages that stay in place are the largest set of pages the rules allow in their current
+    /// order. Finding them is exponential in the worst case, but fast for updates like the ones of
+    /// the puzzle.
+    fn repair(&self, page_numbers: &[PageNumber]) -> Result<Repair, CycleError> {
+        // fail early on cycles, there is no valid order to repair towards
+        self.order(page_numbers.to_vec())?;
+
+        let reachability = self.reachability(page_numbers);
+
+        // pages that cannot both stay where they are
+        let mut conflicts = vec![BitSet::default(); page_numbers.len()];
+        for (i, earlier) in page_numbers.iter().enumerate() {
+            for (j, later) in page_numbers.iter().enumerate().skip(i + 1) {
+                if reachability.reaches(*later, *earlier) {
+                    conflicts[i].insert(j);
+                    conflicts[j].insert(i);
+                }
+            }
+        }
+
+        let mut all_pages = BitSet::default();
+        for i in 0..page_numbers.len() {
+            all_pages.insert(i);
+        }
+        let mut kept_pages = BitSet::default();
+        Self::largest_conflict_free(&conflicts, all_pages, BitSet::default(), &mut kept_pages);
+
+        let kept_positions: Vec<usize> = kept_pages.iter().collect();
+        let kept_page_numbers: HashSet<PageNumber> =
+            kept_positions.iter().map(|i| page_numbers[*i]).collect();
+        let kept_order: Vec<(usize, usize)> = kept_positions
+            .windows(2)
+            .map(|window| (window[0], window[1]))
+            .collect();
+
+        let repaired = self.order_with(page_numbers, &kept_order)?;
+
+        // move every other page right behind the page preceding it in the repaired update, which
+        // is either kept in place or has already been moved
+        let mut current = page_numbers.to_vec();
+        let mut moves = Vec::new();
+
+        for (i, page_number) in repaired.iter().enumerate() {
+            if kept_page_numbers.contains(page_number) {
+                continue;
+            }
+
+            let from = current
+                .iter()
+                .position(|current_page| current_page == page_number)
+                .unwrap();
+            current.remove(from);
+            let to = match i {
+                0 => 0,
+                _ => {
+                    current
+                        .iter()
+                        .position(|current_page| *current_page == repaired[i - 1])
+                        .unwrap()
+                        + 1
+                }
+            };
+            current.insert(to, *page_number);
+
+            moves.push(Move {
+                page: *page_number,
+                from,
+                to,
+            });
+        }
+
+        debug_assert_eq!(current, repaired);
+
+        Ok(Repair { moves, repaired })
+    }
+
+    /// Branch and bound search for the largest set of `candidates` without two conflicting pages.
+    fn largest_conflict_free(
+        conflicts: &[BitSet],
+        candidates: BitSet,
+        chosen: BitSet,
+        best: &mut BitSet,
+    ) {
+        if chosen.len() + candidates.len() <= best.len() {
+            return;
+        }
+
+        let Some(i) = candidates.first() else {
+            *best = chosen;
+            return;
+        };
+        let mut remaining = candidates;
+        remaining.remove(i);
+
+        let mut chosen_with_i = chosen.clone();
+        chosen_with_i.insert(i);
+        Self::largest_conflict_free(
+            conflicts,
+            remaining.difference(&conflicts[i]),
+            chosen_with_i,
+            best,
+        );
+
+        if remaining.intersects(&conflicts[i]) {
+            Self::largest_conflict_free(conflicts, remaining, chosen, best);
+        }
+    }
+
+    /// Renders the rules as a Graphviz DOT graph.
+    ///
+    /// If `update` is given, only its pages and the rules between them are included, and rules the
+    /// update violates are drawn in red.
+    fn to_dot(&self, update: Option<&[PageNumber]>) -> String {
+        let positions: Option<HashMap<PageNumber, usize>> = update.map(|page_numbers| {
+            page_numbers
+                .iter()
+                .enumerate()
+                .map(|(i, page_number)| (*page_number, i))
+                .collect()
+        });
+        let is_included = |page_number: &PageNumber| {
+            positions
+                .as_ref()
+                .is_none_or(|positions| positions.contains_key(page_number))
+        };
+
+        let mut page_numbers: Vec<PageNumber> = match update {
+            Some(page_numbers) => page_numbers.to_vec(),
+            None => self.nodes.keys().copied().collect(),
+        };
+        page_numbers.sort();
+
+        let mut dot = String::from("digraph rules {\n");
+
+        for page_number in &page_numbers {
+            dot.push_str(&format!("    {};\n", page_number));
+        }
+
+        for page_number in &page_numbers {
+            let Some(page) = self.nodes.get(page_number) else {
+                continue;
+            };
+
+            let mut dependent_pages: Vec<PageNumber> = page
+                .dependent_pages
+                .iter()
+                .copied()
+                .filter(is_included)
+                .collect();
+            dependent_pages.sort();
+
+            for dependent_page in dependent_pages {
+                let is_violated = positions
+                    .as_ref()
+                    .is_some_and(|positions| positions[&page.id] > positions[&dependent_page]);
+
+                if is_violated {
+                    dot.push_str(&format!(
+                        "    {} -> {} [color=red];\n",
+                        page.id, dependent_page
+                    ));
+                } else {
+                    dot.push_str(&format!("    {} -> {};\n", page.id, dependent_page));
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    /// Finds a cycle among the pages a topological sort could not order.
+    ///
+    /// Each of these pages still waits for at least one other page that could not be ordered, so
+    /// following those prerequisites backwards has to run into a page seen before.
+    fn find_cycle(prerequisites: &[Vec<usize>], num_prerequisites: &[usize]) -> Vec<usize> {
+        let is_unordered = |i: usize| num_prerequisites[i] > 0;
+
+        let mut i = (0..num_prerequisites.len())
+            .find(|i| is_unordered(*i))
+            .expect("no unordered page left");
+        let mut walk = Vec::new();
+
+        while !walk.contains(&i) {
+            walk.push(i);
+            i = prerequisites[i]
+                .iter()
+                .copied()
+                .find(|prerequisite_i| is_unordered(*prerequisite_i))
+                .expect("unordered page without unordered prerequisite");
+        }
+
+        let start = walk.iter().position(|walked_i| *walked_i == i).unwrap();
+        let mut cycle = walk.split_off(start);
+        cycle.reverse();
+
+        cycle
+    }
+}
+
+/// Updates longer than this are not counted, as counting takes time and memory exponential in
+/// the number of pages.
+const MAX_COUNTED_PAGES: usize = 20;
+
+/// Iterator over the valid orders of an update, see [`Graph::orders`].
+///
+/// Orders are built up by backtracking, always trying pages in their original order first.
+struct Orders {
+    page_numbers: Vec<PageNumber>,
+    prerequisites: Vec<BitSet>,
+    /// Positions of the pages placed so far.
+    prefix: Vec<usize>,
+    placed: BitSet,
+    /// For each length of the prefix, the position of the next page to try appending.
+    next_candidates: Vec<usize>,
+    done: bool,
+}
+
+impl Iterator for Orders {
+    type Item = Vec<PageNumber>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let depth = self.prefix.len();
+
+            if depth == self.page_numbers.len() {
+                let order = self.prefix.iter().map(|i| self.page_numbers[*i]).collect();
+                self.backtrack();
+                return Some(order);
+            }
+
+            let candidate = (self.next_candidates[depth]..self.page_numbers.len()).find(|i| {
+                !self.placed.contains(*i) && self.prerequisites[*i].is_subset(&self.placed)
+            });
+
+            match candidate {
+                Some(i) => {
+                    self.next_candidates[depth] = i + 1;
+                    self.next_candidates[depth + 1] = 0;
+                    self.prefix.push(i);
+                    self.placed.insert(i);
+                }
+                None => self.backtrack(),
+            }
+        }
+
+        None
+    }
+}
+
+impl Orders {
+    fn backtrack(&mut self) {
+        match self.prefix.pop() {
+            Some(i) => self.placed.remove(i),
+            None => self.done = true,
+        }
     }
 }
 
-pub fn solve() {
-    let input = get_input();
+/// The moves making an update valid, applied one after another.
+#[derive(Debug)]
+struct Repair {
+    moves: Vec<Move>,
+    repaired: Vec<PageNumber>,
+}
+
+/// Takes the page at index `from` out of the update and inserts it at index `to`.
+#[derive(Debug)]
+struct Move {
+    page: PageNumber,
+    from: usize,
+    to: usize,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "move page {} from position {} to position {}",
+            self.page,
+            self.from + 1,
+            self.to + 1
+        )
+    }
+}
+
+/// Two pages of an update that appear in an order the rules forbid.
+#[derive(Debug)]
+struct Violation {
+    earlier: PageNumber,
+    earlier_position: usize,
+    later: PageNumber,
+    later_position: usize,
+    /// The chain of rules requiring `later` to come before `earlier`, starting at `later`. A single
+    /// rule is a path of two pages.
+    path: Vec<PageNumber>,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "page {} at position {} must come before page {} at position {}",
+            self.later,
+            self.later_position + 1,
+            self.earlier,
+            self.earlier_position + 1
+        )?;
+
+        if let [left, right] = self.path[..] {
+            write!(f, " (rule {}|{})", left, right)
+        } else {
+            let path: Vec<String> = self.path.iter().map(PageNumber::to_string).collect();
+            write!(f, " (via {})", path.join(" -> "))
+        }
+    }
+}
+
+/// The rules restricted to the pages of an update contain a cycle, so the update cannot be ordered.
+#[derive(Debug)]
+struct CycleError {
+    /// The pages of the cycle, each of which has to come before the next one and the last one
+    /// before the first one.
+    cycle: Vec<PageNumber>,
+}
+
+impl Display for CycleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ordering rules contain a cycle: ")?;
+        for page_number in &self.cycle {
+            write!(f, "{} -> ", page_number)?;
+        }
+        write!(f, "{}", self.cycle[0])
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+pub fn solve(args: &Args) {
+    let input = match get_input(&fetch::selected_input_path(args, 5)) {
+        Ok(input) => input,
+        Err(error) => {
+            println!("Invalid input: {}", error);
+            return;
+        }
+    };
     solve_puzzle_1(input.clone());
-    solve_puzzle_2(input);
+    solve_puzzle_2(input.clone());
+
+    if args.flag("explain") {
+        explain(input.clone());
+    }
+
+    if args.flag("repair") {
+        repair(input.clone());
+    }
+
+    if args.flag("orders") {
+        list_orders(input.clone(), args.value("orders").unwrap_or(0));
+    }
+
+    if args.flag("dot") {
+        export_dot(input.clone(), args.value("dot"), args.value("dot-update"));
+    }
+
+    if args.flag("drop-rule") || args.flag("add-rule") {
+        what_if(input, args.value("drop-rule"), args.value("add-rule"));
+    }
 }
 
 fn solve_puzzle_1(input: Input) {
@@ -127,9 +905,10 @@ fn solve_puzzle_1(input: Input) {
         }
     }
 
-    println!(
-        "Puzzle 1: Sum of valid ordering middle page numbers: {}",
-        sum_of_valid_middle
+    output::print_answer(
+        1,
+        "Sum of valid ordering middle page numbers",
+        sum_of_valid_middle,
     );
 }
 
@@ -138,84 +917,962 @@ fn solve_puzzle_2(input: Input) {
 
     let mut sum_of_reordered_middle = 0;
 
-    for update in &input.updates {
+    for (i, update) in input.updates.iter().enumerate() {
         if !dependency_graph.is_valid_ordering(update) {
-            let ordered_page_numbers = dependency_graph.order(update.clone());
+            let ordered_page_numbers = match dependency_graph.order(update.clone()) {
+                Ok(ordered_page_numbers) => ordered_page_numbers,
+                Err(error) => {
+                    println!("Skipping update {}: {}", i + 1, error);
+                    continue;
+                }
+            };
             let middle_page_number = ordered_page_numbers
                 .get(update.len() / 2)
                 .expect("could not get middle page number");
 
+            let possible_middle_pages = dependency_graph.possible_middle_pages(update);
+            if possible_middle_pages.len() > 1 {
+                let possible_middle_pages: Vec<String> = possible_middle_pages
+                    .iter()
+                    .map(PageNumber::to_string)
+                    .collect();
+                println!(
+                    "Update {}: middle page is ambiguous, could be any of {}",
+                    i + 1,
+                    possible_middle_pages.join(",")
+                );
+            }
+
             sum_of_reordered_middle += middle_page_number;
         }
     }
 
+    output::print_answer(
+        2,
+        "Sum of reordered middle page numbers",
+        sum_of_reordered_middle,
+    );
+}
+
+fn explain(input: Input) {
+    let dependency_graph = Graph::new(input.orderings);
+
+    for (i, update) in input.updates.iter().enumerate() {
+        let violations = dependency_graph.violations(update);
+        if violations.is_empty() {
+            continue;
+        }
+
+        let update: Vec<String> = update.iter().map(PageNumber::to_string).collect();
+        println!("Update {} ({}) is invalid:", i + 1, update.join(","));
+        for violation in violations {
+            println!("  {}", violation);
+        }
+    }
+}
+
+fn repair(input: Input) {
+    let dependency_graph = Graph::new(input.orderings);
+
+    let mut total_moves = 0;
+    let mut total_reordered = 0;
+
+    for (i, update) in input.updates.iter().enumerate() {
+        if dependency_graph.is_valid_ordering(update) {
+            continue;
+        }
+
+        let (repair, ordered_page_numbers) = match (
+            dependency_graph.repair(update),
+            dependency_graph.order(update.clone()),
+        ) {
+            (Ok(repair), Ok(ordered_page_numbers)) => (repair, ordered_page_numbers),
+            (Err(error), _) | (_, Err(error)) => {
+                println!("Skipping update {}: {}", i + 1, error);
+                continue;
+            }
+        };
+
+        let num_reordered = update
+            .iter()
+            .zip(&ordered_page_numbers)
+            .filter(|(page, ordered_page)| page != ordered_page)
+            .count();
+        total_moves += repair.moves.len();
+        total_reordered += num_reordered;
+
+        let repaired: Vec<String> = repair.repaired.iter().map(PageNumber::to_string).collect();
+        println!(
+            "Update {}: {} move(s) to {}, full reorder changes {} position(s)",
+            i + 1,
+            repair.moves.len(),
+            repaired.join(","),
+            num_reordered
+        );
+        for page_move in &repair.moves {
+            println!("  {}", page_move);
+        }
+    }
+
     println!(
-        "Puzzle 2: Sum of reordered middle page numbers: {}",
-        sum_of_reordered_middle
+        "Repairs: {} move(s) in total, full reorders change {} position(s) in total",
+        total_moves, total_reordered
     );
 }
 
-type PageNumber = u32;
+fn list_orders(input: Input, max_listed: usize) {
+    let dependency_graph = Graph::new(input.orderings);
+
+    for (i, update) in input.updates.iter().enumerate() {
+        match dependency_graph.has_unique_order(update) {
+            Ok(true) => {
+                println!("Update {}: unique order", i + 1);
+                continue;
+            }
+            Ok(false) => {}
+            Err(error) => {
+                println!("Update {}: {}", i + 1, error);
+                continue;
+            }
+        }
+
+        let num_orders = match dependency_graph.count_orders(update) {
+            Some(num_orders) => num_orders.to_string(),
+            None => String::from("too many to count"),
+        };
+        let possible_middle_pages: Vec<String> = dependency_graph
+            .possible_middle_pages(update)
+            .iter()
+            .map(PageNumber::to_string)
+            .collect();
+        println!(
+            "Update {}: {} orders, middle page {}",
+            i + 1,
+            num_orders,
+            match possible_middle_pages.len() {
+                1 => format!("is always {}", possible_middle_pages[0]),
+                _ => format!("is ambiguous ({})", possible_middle_pages.join(",")),
+            }
+        );
+
+        for order in dependency_graph.orders(update).take(max_listed) {
+            let order: Vec<String> = order.iter().map(PageNumber::to_string).collect();
+            println!("  {}", order.join(","));
+        }
+    }
+}
+
+/// Writes the rule graph to `path`, or prints it if no path is given. `update` is the number of
+/// the update to restrict the graph to, starting at 1.
+fn export_dot(input: Input, path: Option<PathBuf>, update: Option<usize>) {
+    let dependency_graph = Graph::new(input.orderings);
 
-enum InputSections {
-    OrderingRules,
-    Updates,
+    let highlighted = match update {
+        Some(update) => match update
+            .checked_sub(1)
+            .and_then(|index| input.updates.get(index))
+        {
+            Some(pages) => Some(pages.as_slice()),
+            None => {
+                println!("There is no update {}", update);
+                return;
+            }
+        },
+        None => None,
+    };
+    let dot = dependency_graph.to_dot(highlighted);
+
+    match path {
+        Some(path) => {
+            fs::write(&path, dot).expect("could not write graph for day 5");
+            println!("Graph written to {}", path.display());
+        }
+        None => print!("{}", dot),
+    }
 }
 
-#[derive(Debug, Clone)]
+/// Shows how the answers change if a rule is dropped or added.
+fn what_if(input: Input, dropped_rule: Option<Ordering>, added_rule: Option<Ordering>) {
+    let mut dependency_graph = Graph::new(input.orderings);
+    let validity_before: Vec<bool> = input
+        .updates
+        .iter()
+        .map(|update| dependency_graph.is_valid_ordering(update))
+        .collect();
+    let sums_before = middle_page_sums(&dependency_graph, &input.updates);
+
+    if let Some(rule) = dropped_rule {
+        if !dependency_graph.remove_rule(&rule) {
+            println!("Rule {}|{} does not exist", rule.left, rule.right);
+        }
+    }
+
+    if let Some(rule) = added_rule {
+        if !dependency_graph.add_rule(&rule) {
+            println!("Rule {}|{} already exists", rule.left, rule.right);
+        }
+    }
+
+    let sums_after = middle_page_sums(&dependency_graph, &input.updates);
+
+    for (i, update) in input.updates.iter().enumerate() {
+        let is_valid = dependency_graph.is_valid_ordering(update);
+        if is_valid != validity_before[i] {
+            println!(
+                "Update {} becomes {}",
+                i + 1,
+                if is_valid { "valid" } else { "invalid" }
+            );
+        }
+    }
+
+    println!(
+        "Puzzle 1: Sum of valid ordering middle page numbers: {} -> {}",
+        sums_before.0, sums_after.0
+    );
+    println!(
+        "Puzzle 2: Sum of reordered middle page numbers: {} -> {}",
+        sums_before.1, sums_after.1
+    );
+}
+
+/// Sums of the middle pages of the valid updates and of the reordered invalid updates, skipping
+/// updates that cannot be ordered.
+fn middle_page_sums(dependency_graph: &Graph, updates: &[Vec<PageNumber>]) -> (u32, u32) {
+    let mut sum_of_valid_middle = 0;
+    let mut sum_of_reordered_middle = 0;
+
+    for update in updates {
+        if dependency_graph.is_valid_ordering(update) {
+            sum_of_valid_middle += update[update.len() / 2];
+        } else if let Ok(ordered_page_numbers) = dependency_graph.order(update.clone()) {
+            sum_of_reordered_middle += ordered_page_numbers[update.len() / 2];
+        }
+    }
+
+    (sum_of_valid_middle, sum_of_reordered_middle)
+}
+
+type PageNumber = u32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Ordering {
     left: PageNumber,
     right: PageNumber,
 }
 
+impl FromStr for Ordering {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_ordering(Line::new(1, s))
+            .map_err(|error| format!("invalid rule '{}': {}", s, error.kind))
+    }
+}
+
+/// Parses a rule like `47|53`.
+fn parse_ordering(line: Line) -> Result<Ordering, ParseError> {
+    let [left, right] = line.array(Separator::Str("|"))?;
+
+    Ok(Ordering { left, right })
+}
+
 #[derive(Debug, Clone)]
 struct Input {
     orderings: Vec<Ordering>,
     updates: Vec<Vec<PageNumber>>,
 }
 
-fn get_input() -> Input {
-    let file = File::open("./src/resources/day_5.txt").expect("could not open file for day 5");
-    let buf_reader = BufReader::new(file);
+fn get_input(path: &Path) -> Result<Input, InputError> {
+    let input = fs::read_to_string(path).expect("could not open file for day 5");
+
+    parse_input(&input)
+}
+
+/// Parses the rules and updates, rejecting anything that does not strictly follow the format of
+/// the puzzle input. Blank lines at the end of the input are ignored.
+fn parse_input(input: &str) -> Result<Input, InputError> {
+    let mut sections = parse::sections(input);
+    while sections.len() > 2
+        && sections
+            .last()
+            .is_some_and(|section| section.lines.is_empty())
+    {
+        sections.pop();
+    }
+
+    let [rules, updates] = &sections[..] else {
+        return match sections.get(2) {
+            Some(extra_section) => Err(InputError::ExtraSeparator {
+                line: extra_section
+                    .separator_line
+                    .expect("only the first section has no separator"),
+            }),
+            None => Err(InputError::MissingSeparator),
+        };
+    };
 
-    let mut state = InputSections::OrderingRules;
     let mut orderings = Vec::new();
-    let mut updates = Vec::new();
+    let mut rule_lines = HashMap::new();
 
-    for line in buf_reader.lines() {
-        let line = line.expect("could not read line");
-        if line.is_empty() {
-            state = InputSections::Updates;
-            continue;
+    for line in &rules.lines {
+        let ordering = parse_ordering(*line)?;
+
+        if ordering.left == ordering.right {
+            return Err(InputError::SelfReferentialRule {
+                line: line.number,
+                page: ordering.left,
+            });
         }
 
-        match state {
-            InputSections::OrderingRules => {
-                let mut split = line.split('|');
-                let left = split
-                    .next()
-                    .expect("invalid input format")
-                    .parse::<u32>()
-                    .expect("could not parse number");
-                let right = split
-                    .next()
-                    .expect("invalid input format")
-                    .parse::<u32>()
-                    .expect("could not parse number");
+        if let Some(first_line) = rule_lines.insert(ordering.clone(), line.number) {
+            return Err(InputError::DuplicateRule {
+                line: line.number,
+                first_line,
+            });
+        }
 
-                orderings.push(Ordering { left, right })
+        orderings.push(ordering)
+    }
+
+    let updates = updates
+        .lines
+        .iter()
+        .map(|line| {
+            let page_numbers = line.list::<PageNumber>(Separator::Str(","))?;
+
+            if page_numbers.len() % 2 == 0 {
+                return Err(InputError::NoMiddlePage {
+                    line: line.number,
+                    len: page_numbers.len(),
+                });
             }
-            InputSections::Updates => {
-                let page_numbers = line
-                    .split(',')
-                    .map(|str| str.parse::<u32>())
-                    .map(|result| result.expect("could not parse number"))
-                    .collect::<Vec<PageNumber>>();
 
-                updates.push(page_numbers);
+            let mut seen_pages = HashSet::with_capacity(page_numbers.len());
+            if let Some(page) = page_numbers.iter().find(|page| !seen_pages.insert(**page)) {
+                return Err(InputError::DuplicatePage {
+                    line: line.number,
+                    page: *page,
+                });
+            }
+
+            Ok(page_numbers)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Input { orderings, updates })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum InputError {
+    MissingSeparator,
+    ExtraSeparator { line: usize },
+    SelfReferentialRule { line: usize, page: PageNumber },
+    DuplicateRule { line: usize, first_line: usize },
+    Parse(ParseError),
+    NoMiddlePage { line: usize, len: usize },
+    DuplicatePage { line: usize, page: PageNumber },
+}
+
+impl Display for InputError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputError::MissingSeparator => {
+                write!(f, "missing blank line between the rules and the updates")
+            }
+            InputError::ExtraSeparator { line } => {
+                write!(f, "line {}: unexpected second blank line", line)
+            }
+            InputError::SelfReferentialRule { line, page } => {
+                write!(f, "line {}: page {} cannot come before itself", line, page)
+            }
+            InputError::DuplicateRule { line, first_line } => write!(
+                f,
+                "line {}: duplicate of the rule on line {}",
+                line, first_line
+            ),
+            InputError::Parse(error) => write!(f, "{}", error),
+            InputError::NoMiddlePage { line, len } => write!(
+                f,
+                "line {}: update has {} pages and therefore no middle page",
+                line, len
+            ),
+            InputError::DuplicatePage { line, page } => {
+                write!(f, "line {}: page {} appears more than once", line, page)
             }
         }
     }
+}
+
+impl std::error::Error for InputError {}
+
+impl From<ParseError> for InputError {
+    fn from(error: ParseError) -> Self {
+        InputError::Parse(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{Location, ParseErrorKind};
+    use std::{env, process};
+
+    /// The example of the puzzle description.
+    const EXAMPLE: &str = "\
+47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47
+";
+
+    fn example() -> (Graph, Vec<Vec<PageNumber>>) {
+        let input = parse_input(EXAMPLE).unwrap();
 
-    Input { orderings, updates }
+        (Graph::new(input.orderings), input.updates)
+    }
+
+    fn rules(rules: &[(PageNumber, PageNumber)]) -> Graph {
+        Graph::new(
+            rules
+                .iter()
+                .map(|(left, right)| Ordering {
+                    left: *left,
+                    right: *right,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn parse_rules_and_updates() {
+        let input = parse_input("47|53\n97|13\n\n75,47,61\n97,13,29\n").unwrap();
+
+        assert_eq!(
+            input.orderings,
+            [
+                Ordering {
+                    left: 47,
+                    right: 53
+                },
+                Ordering {
+                    left: 97,
+                    right: 13
+                },
+            ]
+        );
+        assert_eq!(input.updates, [vec![75, 47, 61], vec![97, 13, 29]]);
+    }
+
+    #[test]
+    fn trailing_blank_lines_are_ignored() {
+        let expected = parse_input("47|53\n\n75,47,53\n").unwrap().updates;
+
+        for input in ["47|53\n\n75,47,53\n\n", "47|53\n\n75,47,53\n\n\n  \n"] {
+            assert_eq!(parse_input(input).unwrap().updates, expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn missing_separator() {
+        assert_eq!(
+            parse_input("47|53\n75,47,53\n").unwrap_err(),
+            InputError::MissingSeparator
+        );
+    }
+
+    #[test]
+    fn extra_separator() {
+        assert_eq!(
+            parse_input("47|53\n\n75,47,53\n\n97,13,29\n").unwrap_err(),
+            InputError::ExtraSeparator { line: 4 }
+        );
+        assert_eq!(
+            parse_input("47|53\n\n\n75,47,53\n").unwrap_err(),
+            InputError::ExtraSeparator { line: 3 }
+        );
+    }
+
+    #[test]
+    fn self_referential_rule() {
+        assert_eq!(
+            parse_input("47|53\n53|53\n\n75,47,53\n").unwrap_err(),
+            InputError::SelfReferentialRule { line: 2, page: 53 }
+        );
+    }
+
+    #[test]
+    fn duplicate_rule() {
+        assert_eq!(
+            parse_input("47|53\n97|13\n47|53\n\n75,47,53\n").unwrap_err(),
+            InputError::DuplicateRule {
+                line: 3,
+                first_line: 1
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert_eq!(
+            parse_input("47|53\n47|x3\n\n75,47,53\n").unwrap_err(),
+            InputError::Parse(ParseError {
+                location: Location { line: 2, column: 4 },
+                kind: ParseErrorKind::InvalidValue("x3".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_input("47|53\n\n75,47,53\n75,,53\n").unwrap_err(),
+            InputError::Parse(ParseError {
+                location: Location { line: 4, column: 4 },
+                kind: ParseErrorKind::MissingValue,
+            })
+        );
+    }
+
+    #[test]
+    fn no_middle_page() {
+        assert_eq!(
+            parse_input("47|53\n\n75,47,53\n75,47\n").unwrap_err(),
+            InputError::NoMiddlePage { line: 4, len: 2 }
+        );
+    }
+
+    #[test]
+    fn duplicate_page() {
+        let error = parse_input("53|47\n\n75,47,53\n47,53,47\n").unwrap_err();
+
+        assert_eq!(error, InputError::DuplicatePage { line: 4, page: 47 });
+        assert_eq!(error.to_string(), "line 4: page 47 appears more than once");
+    }
+
+    #[test]
+    fn example_valid_updates() {
+        let (graph, updates) = example();
+
+        let valid: Vec<bool> = updates
+            .iter()
+            .map(|update| graph.is_valid_ordering(update))
+            .collect();
+        assert_eq!(valid, [true, true, true, false, false, false]);
+
+        let sum: PageNumber = updates
+            .iter()
+            .filter(|update| graph.is_valid_ordering(update))
+            .map(|update| update[update.len() / 2])
+            .sum();
+        assert_eq!(sum, 143);
+    }
+
+    #[test]
+    fn example_reordered_updates() {
+        let (graph, updates) = example();
+
+        let reordered: Vec<Vec<PageNumber>> = updates
+            .iter()
+            .filter(|update| !graph.is_valid_ordering(update))
+            .map(|update| graph.order(update.clone()).unwrap())
+            .collect();
+        assert_eq!(
+            reordered,
+            [
+                vec![97, 75, 47, 61, 53],
+                vec![61, 29, 13],
+                vec![97, 75, 47, 29, 13],
+            ]
+        );
+
+        let sum: PageNumber = reordered
+            .iter()
+            .map(|update| update[update.len() / 2])
+            .sum();
+        assert_eq!(sum, 123);
+    }
+
+    #[test]
+    fn order_keeps_unrelated_pages_in_place() {
+        // whenever several pages could come next, the one appearing first wins
+        let graph = rules(&[(1, 2)]);
+
+        assert_eq!(graph.order(vec![3, 2, 1]).unwrap(), [3, 1, 2]);
+        assert_eq!(graph.order(vec![2, 4, 1, 3, 5]).unwrap(), [4, 1, 2, 3, 5]);
+        assert_eq!(graph.order(vec![6, 7, 8]).unwrap(), [6, 7, 8]);
+    }
+
+    #[test]
+    fn cycle() {
+        let graph = rules(&[(47, 53), (53, 13), (13, 47), (75, 47)]);
+        let rotations = [[47, 53, 13], [53, 13, 47], [13, 47, 53]];
+
+        for update in [vec![47, 53, 13], vec![13, 75, 53, 47, 61]] {
+            let error = graph.order(update.clone()).unwrap_err();
+
+            assert!(
+                rotations.iter().any(|rotation| error.cycle == rotation),
+                "{:?}",
+                error.cycle
+            );
+            let expected = format!(
+                "ordering rules contain a cycle: {} -> {} -> {} -> {}",
+                error.cycle[0], error.cycle[1], error.cycle[2], error.cycle[0]
+            );
+            assert_eq!(error.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn cycles_only_count_within_the_update() {
+        let graph = rules(&[(47, 53), (53, 13), (13, 47)]);
+
+        assert_eq!(graph.order(vec![13, 53]).unwrap(), [53, 13]);
+        assert!(graph.repair(&[47, 53, 13]).is_err());
+        assert!(graph.has_unique_order(&[47, 53, 13]).is_err());
+    }
+
+    fn violation_texts(graph: &Graph, update: &[PageNumber]) -> Vec<String> {
+        graph
+            .violations(update)
+            .iter()
+            .map(Violation::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn violations() {
+        let (graph, _) = example();
+
+        assert_eq!(
+            violation_texts(&graph, &[75, 97, 47, 61, 53]),
+            ["page 97 at position 2 must come before page 75 at position 1 (rule 97|75)"]
+        );
+        assert!(violation_texts(&graph, &[75, 47, 61, 53, 29]).is_empty());
+    }
+
+    #[test]
+    fn transitive_violations() {
+        let graph = rules(&[(97, 75), (75, 47)]);
+
+        assert_eq!(
+            violation_texts(&graph, &[47, 97, 75]),
+            [
+                "page 97 at position 2 must come before page 47 at position 1 (via 97 -> 75 -> 47)",
+                "page 75 at position 3 must come before page 47 at position 1 (rule 75|47)",
+            ]
+        );
+
+        // without the page in between, the rules do not order the other two
+        assert!(violation_texts(&graph, &[47, 97]).is_empty());
+    }
+
+    #[test]
+    fn reachability() {
+        let graph = rules(&[(1, 2), (2, 3), (4, 5)]);
+
+        let reachability = graph.reachability(&[1, 2, 3, 5]);
+        assert!(reachability.reaches(1, 2));
+        assert!(reachability.reaches(1, 3));
+        assert!(!reachability.reaches(3, 1));
+        assert!(!reachability.reaches(1, 5));
+        assert!(!reachability.reaches(1, 1));
+
+        // only rules between pages of the update are followed
+        let reachability = graph.reachability(&[1, 3, 4, 5]);
+        assert!(!reachability.reaches(1, 3));
+        assert!(reachability.reaches(4, 5));
+
+        // pages without rules are reachable from nothing
+        let reachability = graph.reachability(&[1, 9]);
+        assert!(!reachability.reaches(1, 9));
+        assert!(!reachability.reaches(9, 1));
+    }
+
+    #[test]
+    fn reachability_decides_validity() {
+        let graph = rules(&[(1, 2), (2, 3)]);
+
+        assert!(graph.is_valid_ordering(&[1, 2, 3]));
+        assert!(!graph.is_valid_ordering(&[3, 2, 1]));
+        assert!(!graph.is_valid_ordering(&[3, 1, 2]));
+        assert!(graph.is_valid_ordering(&[3, 1]));
+        assert!(graph.is_valid_ordering(&[9, 1, 8]));
+    }
+
+    #[test]
+    fn reachability_across_bitset_words() {
+        let chain: Vec<(PageNumber, PageNumber)> = (1..100).map(|page| (page, page + 1)).collect();
+        let graph = rules(&chain);
+        let update: Vec<PageNumber> = (1..=100).collect();
+
+        let reachability = graph.reachability(&update);
+        assert!(reachability.reaches(1, 100));
+        assert!(!reachability.reaches(100, 1));
+        assert!(graph.is_valid_ordering(&update));
+
+        let mut update = update;
+        update.swap(0, 99);
+        assert!(!graph.is_valid_ordering(&update));
+    }
+
+    fn replay(update: &[PageNumber], moves: &[Move]) -> Vec<PageNumber> {
+        let mut current = update.to_vec();
+        for page_move in moves {
+            assert_eq!(current[page_move.from], page_move.page);
+            let page = current.remove(page_move.from);
+            current.insert(page_move.to, page);
+        }
+
+        current
+    }
+
+    fn permutations(pages: &[PageNumber]) -> Vec<Vec<PageNumber>> {
+        if pages.is_empty() {
+            return vec![Vec::new()];
+        }
+
+        let mut all_permutations = Vec::new();
+        for (i, page) in pages.iter().enumerate() {
+            let mut rest = pages.to_vec();
+            rest.remove(i);
+            for mut permutation in permutations(&rest) {
+                permutation.insert(0, *page);
+                all_permutations.push(permutation);
+            }
+        }
+
+        all_permutations
+    }
+
+    #[test]
+    fn repair_example() {
+        let (graph, updates) = example();
+
+        let repair = graph.repair(&updates[5]).unwrap();
+        assert_eq!(repair.repaired, [97, 75, 47, 29, 13]);
+        assert_eq!(repair.moves.len(), 2);
+        assert_eq!(replay(&updates[5], &repair.moves), repair.repaired);
+
+        let repair = graph.repair(&updates[3]).unwrap();
+        assert_eq!(repair.repaired, [97, 75, 47, 61, 53]);
+        assert_eq!(repair.moves.len(), 1);
+        assert_eq!(
+            repair.moves[0].to_string(),
+            "move page 97 from position 2 to position 1"
+        );
+
+        let repair = graph.repair(&updates[0]).unwrap();
+        assert!(repair.moves.is_empty());
+        assert_eq!(repair.repaired, updates[0]);
+    }
+
+    #[test]
+    fn repair_moves_as_few_pages_as_possible() {
+        // the rules of the example order these pages completely
+        let (graph, _) = example();
+        let sorted = [97, 75, 47, 29, 13];
+        let rank = |page: &PageNumber| sorted.iter().position(|sorted| sorted == page).unwrap();
+
+        for update in permutations(&sorted) {
+            // the pages that stay are the longest run of pages that is already in order
+            let ranks: Vec<usize> = update.iter().map(rank).collect();
+            let mut longest = vec![1; ranks.len()];
+            for i in 0..ranks.len() {
+                for j in 0..i {
+                    if ranks[j] < ranks[i] {
+                        longest[i] = longest[i].max(longest[j] + 1);
+                    }
+                }
+            }
+            let num_moves = ranks.len() - longest.iter().max().unwrap();
+
+            let repair = graph.repair(&update).unwrap();
+            assert_eq!(repair.repaired, sorted, "{:?}", update);
+            assert_eq!(repair.moves.len(), num_moves, "{:?}", update);
+            assert_eq!(replay(&update, &repair.moves), repair.repaired);
+        }
+    }
+
+    #[test]
+    fn repair_partial_orders() {
+        let graph = rules(&[(1, 2), (3, 4)]);
+
+        for update in permutations(&[1, 2, 3, 4, 5]) {
+            let repair = graph.repair(&update).unwrap();
+
+            assert!(graph.is_valid_ordering(&repair.repaired), "{:?}", update);
+            assert_eq!(replay(&update, &repair.moves), repair.repaired);
+
+            let position = |page| update.iter().position(|other| *other == page).unwrap();
+            let num_violated = [(1, 2), (3, 4)]
+                .iter()
+                .filter(|(left, right)| position(*left) > position(*right))
+                .count();
+            assert_eq!(repair.moves.len(), num_violated, "{:?}", update);
+        }
+    }
+
+    #[test]
+    fn orders_of_a_partial_order() {
+        let graph = rules(&[(1, 2)]);
+        let update = [1, 2, 3];
+
+        assert!(!graph.has_unique_order(&update).unwrap());
+        assert_eq!(graph.count_orders(&update), Some(3));
+        assert_eq!(
+            graph.orders(&update).collect::<Vec<_>>(),
+            [vec![1, 2, 3], vec![1, 3, 2], vec![3, 1, 2]]
+        );
+        assert_eq!(graph.possible_middle_pages(&update), [1, 2, 3]);
+    }
+
+    #[test]
+    fn orders_with_a_fixed_middle() {
+        let graph = rules(&[(1, 3), (2, 3), (3, 4), (3, 5)]);
+        let update = [5, 4, 3, 2, 1];
+
+        assert_eq!(graph.count_orders(&update), Some(4));
+        assert_eq!(
+            graph.orders(&update).collect::<Vec<_>>(),
+            [
+                vec![2, 1, 3, 5, 4],
+                vec![2, 1, 3, 4, 5],
+                vec![1, 2, 3, 5, 4],
+                vec![1, 2, 3, 4, 5],
+            ]
+        );
+        assert_eq!(graph.possible_middle_pages(&update), [3]);
+    }
+
+    #[test]
+    fn orders_of_a_total_order() {
+        let (graph, updates) = example();
+
+        assert!(graph.has_unique_order(&updates[5]).unwrap());
+        assert_eq!(graph.count_orders(&updates[5]), Some(1));
+        assert_eq!(
+            graph.orders(&updates[5]).collect::<Vec<_>>(),
+            [vec![97, 75, 47, 29, 13]]
+        );
+        assert_eq!(graph.possible_middle_pages(&updates[5]), [47]);
+    }
+
+    #[test]
+    fn orders_of_long_updates() {
+        let graph = rules(&[(1, 2)]);
+        let update: Vec<PageNumber> = (1..=MAX_COUNTED_PAGES as PageNumber + 1).collect();
+
+        assert_eq!(graph.count_orders(&update), None);
+        assert_eq!(graph.orders(&update).next(), Some(update.clone()));
+        assert_eq!(graph.orders(&update).take(3).count(), 3);
+    }
+
+    #[test]
+    fn dot_of_all_rules() {
+        let graph = rules(&[(47, 53), (97, 13), (97, 47)]);
+
+        assert_eq!(
+            graph.to_dot(None),
+            "digraph rules {\n    13;\n    47;\n    53;\n    97;\n    47 -> 53;\n    97 -> 13;\n    97 -> 47;\n}\n"
+        );
+    }
+
+    #[test]
+    fn dot_of_an_update() {
+        let graph = rules(&[(47, 53), (97, 13), (97, 47)]);
+
+        assert_eq!(
+            graph.to_dot(Some(&[97, 53, 47])),
+            "digraph rules {\n    47;\n    53;\n    97;\n    47 -> 53 [color=red];\n    97 -> 47;\n}\n"
+        );
+    }
+
+    #[test]
+    fn dot_of_a_missing_update() {
+        let input = parse_input(EXAMPLE).unwrap();
+        let path = env::temp_dir().join(format!("advent-of-code-day-5-dot-{}", process::id()));
+
+        for update in [0, input.updates.len() + 1] {
+            export_dot(input.clone(), Some(path.clone()), Some(update));
+            assert!(!path.exists());
+        }
+
+        export_dot(input.clone(), Some(path.clone()), Some(input.updates.len()));
+        let dot = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            dot,
+            Graph::new(input.orderings.clone()).to_dot(input.updates.last().map(Vec::as_slice))
+        );
+    }
+
+    #[test]
+    fn removing_and_adding_rules() {
+        let (mut graph, updates) = example();
+        let rule = Ordering {
+            left: 75,
+            right: 13,
+        };
+
+        assert!(!graph.is_valid_ordering(&[13, 75]));
+        assert!(graph.remove_rule(&rule));
+        assert!(!graph.remove_rule(&rule));
+        assert!(graph.is_valid_ordering(&[13, 75]));
+
+        assert!(graph.add_rule(&rule));
+        assert!(!graph.add_rule(&rule));
+        assert!(!graph.is_valid_ordering(&[13, 75]));
+        assert_eq!(middle_page_sums(&graph, &updates), (143, 123));
+    }
+
+    #[test]
+    fn adding_a_rule_with_a_new_page() {
+        let (mut graph, _) = example();
+        let rule = Ordering {
+            left: 99,
+            right: 53,
+        };
+
+        assert!(graph.is_valid_ordering(&[53, 99]));
+        assert!(graph.add_rule(&rule));
+        assert!(!graph.is_valid_ordering(&[53, 99]));
+        assert!(graph.is_valid_ordering(&[99, 53]));
+
+        assert!(graph.remove_rule(&rule));
+        assert!(graph.is_valid_ordering(&[53, 99]));
+    }
+
+    #[test]
+    fn removing_a_missing_rule() {
+        let (mut graph, _) = example();
+
+        assert!(!graph.remove_rule(&Ordering {
+            left: 13,
+            right: 75
+        }));
+        assert!(!graph.remove_rule(&Ordering { left: 1, right: 2 }));
+        assert!(!graph.is_valid_ordering(&[13, 75]));
+    }
 }
@@ -0,0 +1,70 @@
+use crate::cli::Args;
+use crate::fetch;
+use crate::output;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+pub fn solve(args: &Args) {
+    let input = get_input(&fetch::selected_input_path(args, 6));
+    output::print_answer(1, "Answer", solve_puzzle_1(&input));
+    output::print_answer(2, "Answer", solve_puzzle_2(&input));
+}
+
+#[derive(Debug, Clone)]
+struct Input {
+    lines: Vec<String>,
+}
+
+fn solve_puzzle_1(input: &Input) -> usize {
+    // TODO: solve puzzle 1, this only counts the lines of the input
+    input.lines.len()
+}
+
+fn solve_puzzle_2(input: &Input) -> usize {
+    // TODO: solve puzzle 2, this only counts the lines of the input
+    input.lines.len()
+}
+
+fn get_input(path: &Path) -> Input {
+    let input = File::open(path).expect("input file for day 6 not found");
+    let reader = BufReader::new(input);
+
+    let mut lines = Vec::new();
+
+    for line in reader.lines() {
+        let line = line.expect("could not read next line");
+        lines.push(line);
+    }
+
+    Input { lines }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_PATH: &str = "./src/resources/day_6_example.txt";
+
+    /// The answers to the example of the puzzle description. Remove the `ignore` attribute of a
+    /// test once its answer is filled in.
+    const EXAMPLE_ANSWERS: [Option<usize>; 2] = [None, None];
+
+    #[test]
+    #[ignore = "the answer to the example is missing"]
+    fn example_puzzle_1() {
+        let input = get_input(Path::new(EXAMPLE_PATH));
+        let expected =
+            EXAMPLE_ANSWERS[0].expect("the answer to the example of puzzle 1 is missing");
+        assert_eq!(solve_puzzle_1(&input), expected);
+    }
+
+    #[test]
+    #[ignore = "the answer to the example is missing"]
+    fn example_puzzle_2() {
+        let input = get_input(Path::new(EXAMPLE_PATH));
+        let expected =
+            EXAMPLE_ANSWERS[1].expect("the answer to the example of puzzle 2 is missing");
+        assert_eq!(solve_puzzle_2(&input), expected);
+    }
+}
@@ -1,9 +1,19 @@
+use crate::cli::Args;
+use crate::fetch;
+use crate::output;
+use crate::parse::{self, ParseError, Separator};
 use std::collections::BTreeSet;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-pub fn solve() {
-    let input = get_input();
+use std::fs;
+use std::path::Path;
+
+pub fn solve(args: &Args) {
+    let input = match get_input(&fetch::selected_input_path(args, 7)) {
+        Ok(input) => input,
+        Err(error) => {
+            println!("Invalid input: {}", error);
+            return;
+        }
+    };
     solve_puzzle_1(input.clone());
     solve_puzzle_2(input);
 }
@@ -21,10 +31,7 @@ fn solve_puzzle_1(input: Input) {
         }
     }
 
-    println!(
-        "Puzzle 1: Total calibration result: {}",
-        total_calibration_result
-    );
+    output::print_answer(1, "Total calibration result", total_calibration_result);
 }
 
 fn solve_puzzle_2(input: Input) {
@@ -41,10 +48,7 @@ fn solve_puzzle_2(input: Input) {
         }
     }
 
-    println!(
-        "Puzzle 2: Total calibration result: {}",
-        total_calibration_result
-    );
+    output::print_answer(2, "Total calibration result", total_calibration_result);
 }
 
 #[derive(Debug, Clone)]
@@ -123,28 +127,16 @@ struct Input {
     operations: Vec<Operation>,
 }
 
-fn get_input() -> Input {
-    let file = File::open("./src/resources/day_7.txt").expect("could not open file");
-    let buf_reader = BufReader::new(file);
+fn get_input(path: &Path) -> Result<Input, ParseError> {
+    let input = fs::read_to_string(path).expect("could not open file");
 
     let mut operations = Vec::new();
 
-    for line in buf_reader.lines() {
-        let line = line.expect("could not read line");
-
-        let mut parts = line.split(':');
-
-        let result = parts.next().unwrap().parse::<u64>().unwrap();
-
-        let operands: Vec<u64> = parts
-            .next()
-            .unwrap()
-            .split_whitespace()
-            .map(|s| s.parse().unwrap())
-            .collect();
+    for line in parse::lines(&input) {
+        let (result, operands) = line.key_values(":", Separator::Whitespace)?;
 
         operations.push(Operation { result, operands })
     }
 
-    Input { operations }
+    Ok(Input { operations })
 }
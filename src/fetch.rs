@@ -0,0 +1,204 @@
+use crate::cli::Args;
+use crate::http::{HttpError, Request};
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_BASE_URL: &str = "https://adventofcode.com";
+const DEFAULT_YEAR: u16 = 2024;
+
+/// Environment variable holding the session token, used if `--session` is not given.
+pub const SESSION_VARIABLE: &str = "AOC_SESSION";
+
+const USER_AGENT: &str = "github.com/christianschleifer/advent-of-code-2024";
+
+/// An Advent of Code compatible server together with the session to authenticate with.
+pub struct Endpoint {
+    base_url: String,
+    year: u16,
+    session: String,
+}
+
+impl Endpoint {
+    /// Reads `--base-url`, `--year` and `--session`, falling back to the `AOC_SESSION`
+    /// environment variable for the session token. Returns `None` without a session token.
+    pub fn from_args(args: &Args) -> Option<Endpoint> {
+        let session = args
+            .value("session")
+            .or_else(|| env::var(SESSION_VARIABLE).ok())
+            .filter(|session: &String| !session.is_empty())?;
+
+        Some(Endpoint {
+            base_url: args
+                .value::<String>("base-url")
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            year: args.value("year").unwrap_or(DEFAULT_YEAR),
+            session,
+        })
+    }
+
+    /// The url of a day's puzzle, which `/input` and `/answer` are relative to.
+    pub fn day_url(&self, day: u8) -> String {
+        format!("{}/{}/day/{}", self.base_url, self.year, day)
+    }
+
+    /// A request carrying the session cookie.
+    pub fn request<'a>(&self, request: Request<'a>) -> Request<'a> {
+        request
+            .header("Cookie", format!("session={}", self.session))
+            .header("User-Agent", USER_AGENT.to_string())
+    }
+}
+
+#[derive(Debug)]
+pub enum FetchError {
+    MissingSession,
+    Http(HttpError),
+    Status { status: u16, body: String },
+    Io(io::Error),
+}
+
+impl Display for FetchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::MissingSession => write!(
+                f,
+                "no session token, pass --session or set {}",
+                SESSION_VARIABLE
+            ),
+            FetchError::Http(error) => write!(f, "{}", error),
+            FetchError::Status { status, body } => {
+                write!(
+                    f,
+                    "server responded with status {}: {}",
+                    status,
+                    body.trim()
+                )
+            }
+            FetchError::Io(error) => write!(f, "could not write input: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+impl From<HttpError> for FetchError {
+    fn from(error: HttpError) -> Self {
+        FetchError::Http(error)
+    }
+}
+
+impl From<io::Error> for FetchError {
+    fn from(error: io::Error) -> Self {
+        FetchError::Io(error)
+    }
+}
+
+pub fn input_path(day: u8) -> PathBuf {
+    PathBuf::from(format!("./src/resources/day_{}.txt", day))
+}
+
+/// The file `--input` points to, or else the input of `day`.
+pub fn selected_input_path(args: &Args, day: u8) -> PathBuf {
+    args.value("input").unwrap_or_else(|| input_path(day))
+}
+
+/// Downloads the input of `--day` to `src/resources/day_N.txt`. An input that is already on disk
+/// is never downloaded again, except for the empty file `new-day` creates.
+pub fn fetch(args: &Args) -> Result<(), FetchError> {
+    let day = args
+        .value("day")
+        .expect("--day is required to fetch an input");
+
+    fetch_to(args, day, &input_path(day))
+}
+
+pub(crate) fn fetch_to(args: &Args, day: u8, path: &Path) -> Result<(), FetchError> {
+    let is_cached = fs::metadata(path).is_ok_and(|metadata| metadata.len() > 0);
+    if is_cached {
+        println!("Input for day {} is cached at {}", day, path.display());
+        return Ok(());
+    }
+
+    let endpoint = Endpoint::from_args(args).ok_or(FetchError::MissingSession)?;
+    let url = format!("{}/input", endpoint.day_url(day));
+    let response = endpoint.request(Request::get(&url)).send()?;
+
+    if response.status != 200 {
+        return Err(FetchError::Status {
+            status: response.status,
+            body: response.body,
+        });
+    }
+
+    // write to a temporary file first so that an interrupted download is not taken for the input
+    let partial_path = path.with_extension("txt.part");
+    fs::write(&partial_path, response.body)?;
+    fs::rename(&partial_path, path)?;
+
+    println!("Input for day {} written to {}", day, path.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::stub;
+    use std::process;
+
+    #[test]
+    fn fetch_once() {
+        let (base_url, server) = stub::serve_once(200, "3   4\n4   3\n");
+        let args = Args::parse(
+            ["--base-url", &base_url, "--session", "abc"]
+                .into_iter()
+                .map(String::from),
+        );
+
+        let dir = env::temp_dir().join(format!("advent-of-code-fetch-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("day_3.txt");
+
+        let fetched = fetch_to(&args, 3, &path);
+        let request = server.join().unwrap();
+        let input = fs::read_to_string(&path);
+
+        // the stub server is gone, so downloading again would fail
+        let fetched_again = fetch_to(&args, 3, &path);
+        let input_again = fs::read_to_string(&path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(fetched.is_ok());
+        assert!(request.starts_with("GET /2024/day/3/input HTTP/1.0\r\n"));
+        assert!(request.contains("\r\nCookie: session=abc\r\n"));
+        assert_eq!(input.unwrap(), "3   4\n4   3\n");
+
+        assert!(fetched_again.is_ok());
+        assert_eq!(input_again.unwrap(), "3   4\n4   3\n");
+    }
+
+    #[test]
+    fn fetch_rejects_error_status() {
+        let (base_url, server) = stub::serve_once(404, "Not found");
+        let args = Args::parse(
+            ["--base-url", &base_url, "--session", "abc"]
+                .into_iter()
+                .map(String::from),
+        );
+        let path = env::temp_dir().join(format!("advent-of-code-fetch-{}-404.txt", process::id()));
+
+        let fetched = fetch_to(&args, 3, &path);
+        server.join().unwrap();
+
+        assert!(matches!(
+            fetched,
+            Err(FetchError::Status { status: 404, .. })
+        ));
+        assert!(!path.exists());
+    }
+}
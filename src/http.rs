@@ -0,0 +1,358 @@
+use std::fmt::{Display, Formatter};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A minimal HTTP/1.0 client on top of the standard library, enough to talk to an Advent of Code
+/// compatible server. There is no TLS implementation in the standard library, so `https://` URLs
+/// are requested with the `curl` command instead.
+pub struct Request<'a> {
+    method: &'a str,
+    url: &'a str,
+    headers: Vec<(&'a str, String)>,
+    body: Option<String>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub enum HttpError {
+    InvalidUrl(String),
+    UnsupportedScheme(String),
+    Io(io::Error),
+    /// `curl` is needed for `https://` URLs but could not be run.
+    CurlUnavailable(io::Error),
+    Curl(String),
+    InvalidResponse,
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpError::InvalidUrl(url) => write!(f, "invalid url '{}'", url),
+            HttpError::UnsupportedScheme(url) => write!(
+                f,
+                "only http:// and https:// urls are supported, got '{}'",
+                url
+            ),
+            HttpError::Io(error) => write!(f, "request failed: {}", error),
+            HttpError::CurlUnavailable(error) => {
+                write!(
+                    f,
+                    "https urls are requested with curl, which failed to run: {}",
+                    error
+                )
+            }
+            HttpError::Curl(message) => write!(f, "request failed: {}", message),
+            HttpError::InvalidResponse => write!(f, "invalid response"),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl From<io::Error> for HttpError {
+    fn from(error: io::Error) -> Self {
+        HttpError::Io(error)
+    }
+}
+
+impl<'a> Request<'a> {
+    pub fn get(url: &'a str) -> Self {
+        Request {
+            method: "GET",
+            url,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// A POST request with a body of type `application/x-www-form-urlencoded`.
+    pub fn post_form(url: &'a str, fields: &[(&str, &str)]) -> Self {
+        let body: Vec<String> = fields
+            .iter()
+            .map(|(name, value)| {
+                format!("{}={}", encode_form_value(name), encode_form_value(value))
+            })
+            .collect();
+
+        Request {
+            method: "POST",
+            url,
+            headers: vec![(
+                "Content-Type",
+                "application/x-www-form-urlencoded".to_string(),
+            )],
+            body: Some(body.join("&")),
+        }
+    }
+
+    pub fn header(mut self, name: &'a str, value: String) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    pub fn send(self) -> Result<Response, HttpError> {
+        if self.url.starts_with("https://") {
+            return self.send_with_curl();
+        }
+
+        let (host, path) = split_url(self.url)?;
+
+        let address = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:80", host)
+        };
+
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut request = format!("{} {} HTTP/1.0\r\nHost: {}\r\n", self.method, path, host);
+        for (name, value) in &self.headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        let body = self.body.unwrap_or_default();
+        if self.method != "GET" {
+            request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        request.push_str("\r\n");
+        request.push_str(&body);
+
+        stream.write_all(request.as_bytes())?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+
+        parse_response(&response)
+    }
+
+    /// Sends the request with `curl`. Its options are passed on stdin so that the session cookie
+    /// does not show up in the list of processes.
+    fn send_with_curl(self) -> Result<Response, HttpError> {
+        let mut config = format!(
+            "silent\nshow-error\ninclude\nsuppress-connect-headers\nhttp1.1\nmax-time = {}\n",
+            TIMEOUT.as_secs()
+        );
+        config.push_str(&format!("request = {}\n", quote_config_value(self.method)));
+        for (name, value) in &self.headers {
+            let header = format!("{}: {}", name, value);
+            config.push_str(&format!("header = {}\n", quote_config_value(&header)));
+        }
+        if let Some(body) = &self.body {
+            config.push_str(&format!("data-binary = {}\n", quote_config_value(body)));
+        }
+        config.push_str(&format!("url = {}\n", quote_config_value(self.url)));
+
+        let mut curl = Command::new("curl")
+            .args(["--config", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(HttpError::CurlUnavailable)?;
+
+        curl.stdin
+            .take()
+            .expect("stdin of curl is piped")
+            .write_all(config.as_bytes())?;
+        let output = curl.wait_with_output()?;
+
+        if !output.status.success() {
+            return Err(HttpError::Curl(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        parse_response(&output.stdout)
+    }
+}
+
+/// Quotes a value of a curl config file.
+fn quote_config_value(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for char in value.chars() {
+        match char {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(char),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+/// Splits `http://host[:port]/path` into the host, including the port if given, and the path.
+fn split_url(url: &str) -> Result<(&str, &str), HttpError> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err(if url.contains("://") {
+            HttpError::UnsupportedScheme(url.to_string())
+        } else {
+            HttpError::InvalidUrl(url.to_string())
+        });
+    };
+
+    let (host, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    if host.is_empty() {
+        return Err(HttpError::InvalidUrl(url.to_string()));
+    }
+
+    Ok((host, path))
+}
+
+fn parse_response(response: &[u8]) -> Result<Response, HttpError> {
+    let header_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or(HttpError::InvalidResponse)?;
+    let head =
+        std::str::from_utf8(&response[..header_end]).map_err(|_| HttpError::InvalidResponse)?;
+    let mut body = &response[header_end + 4..];
+
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or(HttpError::InvalidResponse)?;
+
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok());
+    if let Some(content_length) = content_length {
+        body = &body[..content_length.min(body.len())];
+    }
+
+    Ok(Response {
+        status,
+        body: String::from_utf8_lossy(body).into_owned(),
+    })
+}
+
+fn encode_form_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// A server for tests that answers a single request.
+#[cfg(test)]
+pub(crate) mod stub {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Answers the first request to the returned base url with `status` and `body`. Joining the
+    /// handle gives the request that was received.
+    pub(crate) fn serve_once(status: u16, body: &str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("could not bind stub server");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.0 {} Stub\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("no request to the stub server");
+
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !is_complete(&request) {
+                let read = stream.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+
+            stream.write_all(response.as_bytes()).unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        (base_url, handle)
+    }
+
+    /// Whether the head and the body announced in it have been received.
+    fn is_complete(request: &[u8]) -> bool {
+        let request = String::from_utf8_lossy(request);
+        let Some((head, body)) = request.split_once("\r\n\r\n") else {
+            return false;
+        };
+
+        let content_length = head
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .map_or(0, |(_, value)| value.parse().unwrap());
+
+        body.len() >= content_length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn post_form() {
+        let (base_url, server) = stub::serve_once(200, "ok");
+        let url = format!("{}/2024/day/1/answer", base_url);
+
+        let response = Request::post_form(&url, &[("level", "1"), ("answer", "a b&c")])
+            .header("Cookie", "session=abc".to_string())
+            .send()
+            .unwrap();
+        let request = server.join().unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "ok");
+        assert!(request.starts_with("POST /2024/day/1/answer HTTP/1.0\r\n"));
+        assert!(request.contains("\r\nCookie: session=abc\r\n"));
+        assert!(request.ends_with("\r\n\r\nlevel=1&answer=a+b%26c"));
+    }
+
+    #[test]
+    fn unsupported_urls() {
+        assert!(matches!(
+            Request::get("ftp://example.com").send(),
+            Err(HttpError::UnsupportedScheme(_))
+        ));
+        assert!(matches!(
+            Request::get("example.com").send(),
+            Err(HttpError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn curl_config_values() {
+        assert_eq!(
+            quote_config_value("Cookie: a\"b\\c\n"),
+            "\"Cookie: a\\\"b\\\\c\\n\""
+        );
+    }
+}
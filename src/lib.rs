@@ -1,3 +1,4 @@
+pub mod cli;
 pub mod day_1;
 pub mod day_2;
 pub mod day_3;
@@ -5,4 +6,10 @@ pub mod day_4;
 pub mod day_5;
 pub mod day_6;
 pub mod day_7;
+pub mod fetch;
+pub mod http;
 pub mod output;
+pub mod parse;
+pub mod scaffold;
+pub mod submit;
+pub mod watch;
@@ -1,6 +1,47 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+
+/// The day whose results are currently printed, 0 outside of [`wrap_day_results`].
+static CURRENT_DAY: AtomicU8 = AtomicU8::new(0);
+
+static ANSWERS: Mutex<Vec<Answer>> = Mutex::new(Vec::new());
+
+/// The answer to a puzzle as printed by [`print_answer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Answer {
+    pub day: u8,
+    pub puzzle: u8,
+    pub value: String,
+}
+
 pub fn wrap_day_results<T: FnOnce()>(day: u8, day_results: T) {
     println!("----------");
     println!("Day {}", day);
+    CURRENT_DAY.store(day, Ordering::Relaxed);
     day_results();
+    CURRENT_DAY.store(0, Ordering::Relaxed);
     println!("----------");
 }
+
+pub fn wrap_selected_day_results<T: FnOnce()>(selected_day: Option<u8>, day: u8, day_results: T) {
+    if selected_day.is_none_or(|selected_day| selected_day == day) {
+        wrap_day_results(day, day_results);
+    }
+}
+
+/// Prints the answer to a puzzle of the current day and keeps it for [`take_answers`].
+pub fn print_answer(puzzle: u8, description: &str, value: impl Display) {
+    println!("Puzzle {}: {}: {}", puzzle, description, value);
+
+    ANSWERS.lock().expect("answers are poisoned").push(Answer {
+        day: CURRENT_DAY.load(Ordering::Relaxed),
+        puzzle,
+        value: value.to_string(),
+    });
+}
+
+/// Returns the answers printed since the last call.
+pub fn take_answers() -> Vec<Answer> {
+    std::mem::take(&mut *ANSWERS.lock().expect("answers are poisoned"))
+}
@@ -0,0 +1,428 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Where a problem in the input is. Lines and columns start at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub location: Location,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    InvalidValue(String),
+    MissingValue,
+    WrongCount { expected: usize, found: usize },
+    MissingSeparator(String),
+    RowWidth { width: usize, expected_width: usize },
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::InvalidValue(value) => write!(f, "invalid value '{}'", value),
+            ParseErrorKind::MissingValue => write!(f, "missing value"),
+            ParseErrorKind::WrongCount { expected, found } => {
+                write!(f, "expected {} values, found {}", expected, found)
+            }
+            ParseErrorKind::MissingSeparator(separator) => {
+                write!(f, "missing separator '{}'", separator)
+            }
+            ParseErrorKind::RowWidth {
+                width,
+                expected_width,
+            } => write!(
+                f,
+                "row is {} characters wide, expected {}",
+                width, expected_width
+            ),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.location.line, self.location.column, self.kind
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// How the values of a list are separated. Values separated by a string are trimmed.
+#[derive(Debug, Clone, Copy)]
+pub enum Separator<'a> {
+    Whitespace,
+    Str(&'a str),
+}
+
+/// A line of a puzzle input together with its line number.
+#[derive(Debug, Clone, Copy)]
+pub struct Line<'a> {
+    pub number: usize,
+    pub text: &'a str,
+}
+
+pub fn lines(input: &str) -> impl Iterator<Item = Line<'_>> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, text)| Line::new(i + 1, text))
+}
+
+impl<'a> Line<'a> {
+    pub fn new(number: usize, text: &'a str) -> Self {
+        Line { number, text }
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+
+    /// The location of `part`, which has to be a slice of this line.
+    pub fn location(&self, part: &str) -> Location {
+        let offset = (part.as_ptr() as usize)
+            .checked_sub(self.text.as_ptr() as usize)
+            .filter(|offset| *offset <= self.text.len())
+            .expect("part is not a slice of the line");
+
+        Location {
+            line: self.number,
+            column: self.text[..offset].chars().count() + 1,
+        }
+    }
+
+    pub fn error(&self, part: &str, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            location: self.location(part),
+            kind,
+        }
+    }
+
+    pub fn value<T: FromStr>(&self, part: &'a str) -> Result<T, ParseError> {
+        if part.is_empty() {
+            return Err(self.error(part, ParseErrorKind::MissingValue));
+        }
+
+        part.parse()
+            .map_err(|_| self.error(part, ParseErrorKind::InvalidValue(part.to_string())))
+    }
+
+    /// Parses all values of the line, like `3 4 5` or `75,47,61`.
+    pub fn list<T: FromStr>(&self, separator: Separator) -> Result<Vec<T>, ParseError> {
+        self.list_in(self.text, separator)
+    }
+
+    /// Parses exactly `N` values, like the `47|53` of a rule.
+    pub fn array<T: FromStr, const N: usize>(
+        &self,
+        separator: Separator,
+    ) -> Result<[T; N], ParseError> {
+        self.array_in(self.text, separator)
+    }
+
+    /// Parses a key and the list of values after it, like `190: 10 19`.
+    pub fn key_values<K: FromStr, V: FromStr>(
+        &self,
+        key_separator: &str,
+        separator: Separator,
+    ) -> Result<(K, Vec<V>), ParseError> {
+        let (key, values) = self.text.split_once(key_separator).ok_or_else(|| {
+            self.error(
+                &self.text[self.text.len()..],
+                ParseErrorKind::MissingSeparator(key_separator.to_string()),
+            )
+        })?;
+
+        Ok((self.value(key.trim())?, self.list_in(values, separator)?))
+    }
+
+    fn split_in(&self, part: &'a str, separator: Separator) -> Vec<&'a str> {
+        match separator {
+            Separator::Whitespace => part.split_whitespace().collect(),
+            Separator::Str(separator) => part.split(separator).map(str::trim).collect(),
+        }
+    }
+
+    fn list_in<T: FromStr>(
+        &self,
+        part: &'a str,
+        separator: Separator,
+    ) -> Result<Vec<T>, ParseError> {
+        self.split_in(part, separator)
+            .into_iter()
+            .map(|value| self.value(value))
+            .collect()
+    }
+
+    fn array_in<T: FromStr, const N: usize>(
+        &self,
+        part: &'a str,
+        separator: Separator,
+    ) -> Result<[T; N], ParseError> {
+        let values = self.split_in(part, separator);
+        if values.len() != N {
+            return Err(self.error(
+                part,
+                ParseErrorKind::WrongCount {
+                    expected: N,
+                    found: values.len(),
+                },
+            ));
+        }
+
+        let values: Vec<T> = values
+            .into_iter()
+            .map(|value| self.value(value))
+            .collect::<Result<_, _>>()?;
+
+        Ok(values
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("the number of values was checked")))
+    }
+}
+
+/// Lines between blank lines.
+#[derive(Debug, Clone)]
+pub struct Section<'a> {
+    /// The number of the blank line before the section, `None` for the first section.
+    pub separator_line: Option<usize>,
+    pub lines: Vec<Line<'a>>,
+}
+
+/// Splits the input at every blank line. Consecutive blank lines result in empty sections.
+pub fn sections(input: &str) -> Vec<Section<'_>> {
+    let mut sections = vec![Section {
+        separator_line: None,
+        lines: Vec::new(),
+    }];
+
+    for line in lines(input) {
+        if line.is_blank() {
+            sections.push(Section {
+                separator_line: Some(line.number),
+                lines: Vec::new(),
+            });
+        } else {
+            sections
+                .last_mut()
+                .expect("there is always a section")
+                .lines
+                .push(line);
+        }
+    }
+
+    sections
+}
+
+/// Parses a rectangular grid of characters, one row per line.
+pub fn char_grid(input: &str) -> Result<Vec<Vec<char>>, ParseError> {
+    let mut rows: Vec<Vec<char>> = Vec::new();
+
+    for line in lines(input) {
+        let row: Vec<char> = line.text.chars().collect();
+
+        if let Some(first_row) = rows.first() {
+            if row.len() != first_row.len() {
+                let column = row.len().min(first_row.len());
+                return Err(ParseError {
+                    location: Location {
+                        line: line.number,
+                        column: column + 1,
+                    },
+                    kind: ParseErrorKind::RowWidth {
+                        width: row.len(),
+                        expected_width: first_row.len(),
+                    },
+                });
+            }
+        }
+
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(line: usize, column: usize) -> Location {
+        Location { line, column }
+    }
+
+    #[test]
+    fn list() {
+        let line = Line::new(1, "  3 4   5 ");
+        assert_eq!(line.list::<u8>(Separator::Whitespace), Ok(vec![3, 4, 5]));
+
+        let line = Line::new(1, "75, 47 ,61");
+        assert_eq!(line.list::<u8>(Separator::Str(",")), Ok(vec![75, 47, 61]));
+    }
+
+    #[test]
+    fn list_errors() {
+        // the column is the one of the trimmed value
+        let error = Line::new(3, "75, 4x ,61").list::<u8>(Separator::Str(","));
+        assert_eq!(
+            error,
+            Err(ParseError {
+                location: location(3, 5),
+                kind: ParseErrorKind::InvalidValue("4x".to_string()),
+            })
+        );
+
+        // a missing value is located right after the separator before it
+        let error = Line::new(3, "75, ,61").list::<u8>(Separator::Str(","));
+        assert_eq!(
+            error,
+            Err(ParseError {
+                location: location(3, 4),
+                kind: ParseErrorKind::MissingValue,
+            })
+        );
+
+        // columns count characters, not bytes
+        let error = Line::new(1, "ää 1 x").list::<u8>(Separator::Whitespace);
+        assert_eq!(error.unwrap_err().location, location(1, 1));
+        let error = Line::new(1, "1 ä x").list::<u8>(Separator::Whitespace);
+        assert_eq!(error.unwrap_err().location, location(1, 3));
+        let error = Line::new(1, "1 2 ä").list::<u8>(Separator::Whitespace);
+        assert_eq!(error.unwrap_err().location, location(1, 5));
+    }
+
+    #[test]
+    fn array() {
+        let line = Line::new(1, "47|53");
+        assert_eq!(line.array::<u8, 2>(Separator::Str("|")), Ok([47, 53]));
+
+        let error = Line::new(2, "47|53|61").array::<u8, 2>(Separator::Str("|"));
+        assert_eq!(
+            error,
+            Err(ParseError {
+                location: location(2, 1),
+                kind: ParseErrorKind::WrongCount {
+                    expected: 2,
+                    found: 3
+                },
+            })
+        );
+
+        let error = Line::new(2, "47 | x3").array::<u8, 2>(Separator::Str("|"));
+        assert_eq!(
+            error,
+            Err(ParseError {
+                location: location(2, 6),
+                kind: ParseErrorKind::InvalidValue("x3".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn key_values() {
+        let line = Line::new(1, "190: 10 19");
+        assert_eq!(
+            line.key_values::<u64, u64>(":", Separator::Whitespace),
+            Ok((190, vec![10, 19]))
+        );
+
+        let error = Line::new(4, "190: 10 x9").key_values::<u64, u64>(":", Separator::Whitespace);
+        assert_eq!(
+            error,
+            Err(ParseError {
+                location: location(4, 9),
+                kind: ParseErrorKind::InvalidValue("x9".to_string()),
+            })
+        );
+
+        let error = Line::new(4, " x0 : 10").key_values::<u64, u64>(":", Separator::Whitespace);
+        assert_eq!(
+            error,
+            Err(ParseError {
+                location: location(4, 2),
+                kind: ParseErrorKind::InvalidValue("x0".to_string()),
+            })
+        );
+
+        let error = Line::new(4, "190 10 19").key_values::<u64, u64>(":", Separator::Whitespace);
+        assert_eq!(
+            error,
+            Err(ParseError {
+                location: location(4, 10),
+                kind: ParseErrorKind::MissingSeparator(":".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn error_display() {
+        let error = Line::new(12, "1 x").list::<u8>(Separator::Whitespace);
+
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "line 12, column 3: invalid value 'x'"
+        );
+    }
+
+    #[test]
+    fn sections_with_consecutive_blank_lines() {
+        let sections = sections("a\nb\n\n  \nc\n");
+        let texts: Vec<(Option<usize>, Vec<&str>)> = sections
+            .iter()
+            .map(|section| {
+                let lines = section.lines.iter().map(|line| line.text).collect();
+                (section.separator_line, lines)
+            })
+            .collect();
+
+        assert_eq!(
+            texts,
+            vec![
+                (None, vec!["a", "b"]),
+                (Some(3), vec![]),
+                (Some(4), vec!["c"]),
+            ]
+        );
+        assert_eq!(sections[2].lines[0].number, 5);
+    }
+
+    #[test]
+    fn char_grids() {
+        assert_eq!(
+            char_grid("ab\ncd\n"),
+            Ok(vec![vec!['a', 'b'], vec!['c', 'd']])
+        );
+        assert_eq!(char_grid(""), Ok(vec![]));
+
+        assert_eq!(
+            char_grid("abc\nab\n"),
+            Err(ParseError {
+                location: location(2, 3),
+                kind: ParseErrorKind::RowWidth {
+                    width: 2,
+                    expected_width: 3
+                },
+            })
+        );
+        assert_eq!(
+            char_grid("ab\nab\nabcd\n"),
+            Err(ParseError {
+                location: location(3, 3),
+                kind: ParseErrorKind::RowWidth {
+                    width: 4,
+                    expected_width: 2
+                },
+            })
+        );
+    }
+}
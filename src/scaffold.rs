@@ -0,0 +1,394 @@
+use crate::cli::Args;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+const TEMPLATE: &str = include_str!("templates/day.rs.template");
+
+const SOURCE_DIR: &str = "./src";
+
+const IMPORT_PREFIX: &str = "use advent_of_code::{";
+
+/// The line width rustfmt formats to. It wraps lists before they reach the last column.
+const MAX_WIDTH: usize = 100;
+
+/// Creates `src/day_N.rs` from the template together with empty input and example files, and
+/// registers the day in `lib.rs` and the binary. The module is written first, so that `lib.rs`
+/// never declares a module that does not exist. Registration is skipped where the day is already
+/// registered.
+pub fn new_day(args: &Args) -> Result<(), ScaffoldError> {
+    let day: u8 = args.operand(0).expect("usage: new-day N");
+
+    create_day(Path::new(SOURCE_DIR), day)
+}
+
+fn create_day(source_dir: &Path, day: u8) -> Result<(), ScaffoldError> {
+    let module_path = source_dir.join(format!("day_{}.rs", day));
+    if module_path.exists() {
+        return Err(ScaffoldError::Exists(module_path));
+    }
+    fs::write(&module_path, TEMPLATE.replace("{{day}}", &day.to_string()))?;
+    println!("Created {}", module_path.display());
+
+    register_module(&source_dir.join("lib.rs"), day)?;
+    register_solver(&source_dir.join("bin/advent_of_code.rs"), day)?;
+
+    for name in [
+        format!("day_{}.txt", day),
+        format!("day_{}_example.txt", day),
+    ] {
+        let path = source_dir.join("resources").join(name);
+        // never truncate an input that is already there
+        OpenOptions::new().create(true).append(true).open(&path)?;
+        println!("Created {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn register_module(path: &Path, day: u8) -> Result<(), ScaffoldError> {
+    let content = fs::read_to_string(path)?;
+    let lines = declare_module(&content, day)
+        .ok_or_else(|| ScaffoldError::Registration(path.to_path_buf()))?;
+
+    write_lines(path, &content, &lines)
+}
+
+fn register_solver(path: &Path, day: u8) -> Result<(), ScaffoldError> {
+    let content = fs::read_to_string(path)?;
+    let lines = import_solver(&content, day)
+        .ok_or_else(|| ScaffoldError::Registration(path.to_path_buf()))?;
+
+    write_lines(path, &content, &lines)
+}
+
+/// Adds `pub mod day_N;` to `lib.rs`, keeping the modules in the order rustfmt sorts them in.
+/// Returns `None` if there is no module to add it next to.
+fn declare_module(content: &str, day: u8) -> Option<Vec<String>> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let module = format!("day_{}", day);
+
+    fn declared_module(line: &str) -> Option<&str> {
+        line.strip_prefix("pub mod ")?.strip_suffix(';')
+    }
+
+    if lines
+        .iter()
+        .any(|line| declared_module(line) == Some(&module))
+    {
+        return Some(lines);
+    }
+
+    let index = insertion_index(&lines, module.as_str(), declared_module)?;
+    lines.insert(index, format!("pub mod {};", module));
+
+    Some(lines)
+}
+
+/// Imports the module in the binary and prints its results after the days before it. Returns
+/// `None` if the binary has no import or solver to add them next to.
+fn import_solver(content: &str, day: u8) -> Option<Vec<String>> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let module = format!("day_{}", day);
+
+    // the import may have been wrapped over several lines by rustfmt
+    let import_start = lines
+        .iter()
+        .position(|line| line.starts_with(IMPORT_PREFIX))?;
+    let import_end = (import_start..lines.len()).find(|i| lines[*i].ends_with("};"))?;
+
+    let import = lines[import_start..=import_end].concat();
+    let names = &import[IMPORT_PREFIX.len()..import.len() - "};".len()];
+    let mut names: Vec<&str> = names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    if !names.contains(&module.as_str()) {
+        names.push(&module);
+        names.sort_unstable();
+        let import = format_import(&names);
+        lines.splice(import_start..=import_end, import);
+    }
+
+    let wrapped_day = |line: &str| {
+        line.trim_start()
+            .strip_prefix("output::wrap_selected_day_results(selected_day, ")?
+            .split(',')
+            .next()?
+            .parse::<u8>()
+            .ok()
+    };
+
+    if !lines.iter().any(|line| wrapped_day(line) == Some(day)) {
+        let index = insertion_index(&lines, day, wrapped_day)?;
+        lines.insert(
+            index,
+            format!(
+                "    output::wrap_selected_day_results(selected_day, {}, || {}::solve(args));",
+                day, module
+            ),
+        );
+    }
+
+    Some(lines)
+}
+
+/// Formats the import like rustfmt: on one line if it fits, otherwise with as many names per
+/// line as fit.
+fn format_import(names: &[&str]) -> Vec<String> {
+    let line = format!("{}{}}};", IMPORT_PREFIX, names.join(", "));
+    if line.len() < MAX_WIDTH - 1 {
+        return vec![line];
+    }
+
+    let mut lines = vec![IMPORT_PREFIX.to_string()];
+    let mut line = String::new();
+    for (i, name) in names.iter().enumerate() {
+        // the trailing comma after the last name does not count
+        let separator_len = if i + 1 < names.len() { ",".len() } else { 0 };
+        if !line.is_empty() && line.len() + " ".len() + name.len() + separator_len >= MAX_WIDTH {
+            lines.push(line);
+            line = String::new();
+        }
+
+        if line.is_empty() {
+            line.push_str("   ");
+        }
+        line.push_str(&format!(" {},", name));
+    }
+    lines.push(line);
+    lines.push("};".to_string());
+
+    lines
+}
+
+/// The index to insert the line with `key` at: after the last line with a smaller key, or before
+/// the first line with a key. Returns `None` if no line has a key.
+fn insertion_index<'a, K, F>(lines: &'a [String], key: K, line_key: F) -> Option<usize>
+where
+    K: Ord,
+    F: Fn(&'a str) -> Option<K>,
+{
+    let keys: Vec<(usize, K)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| Some((i, line_key(line)?)))
+        .collect();
+
+    let after_smaller = keys
+        .iter()
+        .filter(|(_, line_key)| *line_key < key)
+        .map(|(i, _)| i + 1)
+        .next_back();
+
+    after_smaller.or_else(|| keys.first().map(|(i, _)| *i))
+}
+
+/// Writes `lines` back to `path`, unless they are still the original `content`.
+fn write_lines(path: &Path, content: &str, lines: &[String]) -> Result<(), ScaffoldError> {
+    let new_content = lines.join("\n") + "\n";
+    if new_content == content {
+        return Ok(());
+    }
+
+    fs::write(path, new_content)?;
+    println!("Registered the day in {}", path.display());
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum ScaffoldError {
+    Exists(PathBuf),
+    /// The file does not have the lines the day is registered next to.
+    Registration(PathBuf),
+    Io(io::Error),
+}
+
+impl Display for ScaffoldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScaffoldError::Exists(path) => write!(f, "{} already exists", path.display()),
+            ScaffoldError::Registration(path) => {
+                write!(
+                    f,
+                    "could not find where to register the day in {}",
+                    path.display()
+                )
+            }
+            ScaffoldError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ScaffoldError {}
+
+impl From<io::Error> for ScaffoldError {
+    fn from(error: io::Error) -> Self {
+        ScaffoldError::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch;
+    use crate::http::stub;
+    use std::{env, process};
+
+    const LIB: &str =
+        "pub mod cli;\npub mod day_1;\npub mod day_2;\npub mod day_7;\npub mod fetch;\n";
+
+    const BINARY: &str = "\
+use advent_of_code::{day_1, day_2, day_3, day_4, day_5, day_7, fetch, output, scaffold, submit};
+
+fn solve(args: &Args) {
+    let selected_day = args.value(\"day\");
+
+    output::wrap_selected_day_results(selected_day, 1, || day_1::solve(args));
+    output::wrap_selected_day_results(selected_day, 5, || day_5::solve(args));
+    output::wrap_selected_day_results(selected_day, 7, || day_7::solve(args));
+}
+";
+
+    #[test]
+    fn modules_are_sorted_like_rustfmt() {
+        let lines = declare_module(LIB, 10).unwrap();
+        assert_eq!(
+            lines,
+            [
+                "pub mod cli;",
+                "pub mod day_1;",
+                "pub mod day_10;",
+                "pub mod day_2;",
+                "pub mod day_7;",
+                "pub mod fetch;",
+            ]
+        );
+
+        let lines = declare_module(LIB, 6).unwrap();
+        assert_eq!(lines[3], "pub mod day_6;");
+    }
+
+    #[test]
+    fn declaring_again_changes_nothing() {
+        let content = declare_module(LIB, 10).unwrap().join("\n") + "\n";
+
+        assert_eq!(
+            declare_module(&content, 10).unwrap().join("\n") + "\n",
+            content
+        );
+        assert_eq!(declare_module(LIB, 2).unwrap().join("\n") + "\n", LIB);
+    }
+
+    #[test]
+    fn modules_need_a_neighbour() {
+        assert_eq!(declare_module("use std::fs;\n", 1), None);
+    }
+
+    #[test]
+    fn imports_are_formatted_like_rustfmt() {
+        // 96 characters still fit on one line
+        let names = [
+            "day_1", "day_2", "day_3", "day_4", "day_5", "day_7", "fetch", "output", "scaffold",
+            "submit",
+        ];
+        assert_eq!(
+            format_import(&names),
+            ["use advent_of_code::{day_1, day_2, day_3, day_4, day_5, day_7, fetch, output, scaffold, submit};"]
+        );
+
+        let names = [
+            "day_1", "day_10", "day_11", "day_2", "day_3", "day_4", "day_5", "day_6", "day_7",
+            "fetch", "output", "scaffold", "submit", "watch",
+        ];
+        assert_eq!(
+            format_import(&names),
+            [
+                "use advent_of_code::{",
+                "    day_1, day_10, day_11, day_2, day_3, day_4, day_5, day_6, day_7, fetch, output, scaffold,",
+                "    submit, watch,",
+                "};",
+            ]
+        );
+    }
+
+    #[test]
+    fn solvers_are_imported_and_called_in_order() {
+        let lines = import_solver(BINARY, 10).unwrap();
+
+        assert_eq!(
+            lines[..4],
+            [
+                "use advent_of_code::{",
+                "    day_1, day_10, day_2, day_3, day_4, day_5, day_7, fetch, output, scaffold, submit,",
+                "};",
+                "",
+            ]
+        );
+        assert_eq!(
+            lines[lines.len() - 2],
+            "    output::wrap_selected_day_results(selected_day, 10, || day_10::solve(args));"
+        );
+
+        let lines = import_solver(BINARY, 6).unwrap();
+        assert_eq!(
+            lines[lines.len() - 4..lines.len() - 1],
+            [
+                "    output::wrap_selected_day_results(selected_day, 5, || day_5::solve(args));",
+                "    output::wrap_selected_day_results(selected_day, 6, || day_6::solve(args));",
+                "    output::wrap_selected_day_results(selected_day, 7, || day_7::solve(args));",
+            ]
+        );
+    }
+
+    #[test]
+    fn importing_again_changes_nothing() {
+        let content = import_solver(BINARY, 10).unwrap().join("\n") + "\n";
+
+        assert_eq!(
+            import_solver(&content, 10).unwrap().join("\n") + "\n",
+            content
+        );
+        assert_eq!(import_solver(BINARY, 5).unwrap().join("\n") + "\n", BINARY);
+    }
+
+    #[test]
+    fn fetch_after_creating_a_day() {
+        let source_dir = env::temp_dir().join(format!("advent-of-code-scaffold-{}", process::id()));
+        fs::create_dir_all(source_dir.join("bin")).unwrap();
+        fs::create_dir_all(source_dir.join("resources")).unwrap();
+        fs::write(source_dir.join("lib.rs"), LIB).unwrap();
+        fs::write(source_dir.join("bin/advent_of_code.rs"), BINARY).unwrap();
+
+        let created = create_day(&source_dir, 8);
+        let input_path = source_dir.join("resources/day_8.txt");
+        let created_input = fs::read_to_string(&input_path);
+
+        let (base_url, server) = stub::serve_once(200, "............\n");
+        let args = Args::parse(
+            ["--base-url", &base_url, "--session", "abc"]
+                .into_iter()
+                .map(String::from),
+        );
+        let fetched = fetch::fetch_to(&args, 8, &input_path);
+        server.join().unwrap();
+        let fetched_input = fs::read_to_string(&input_path);
+
+        let module = fs::read_to_string(source_dir.join("day_8.rs"));
+        let lib = fs::read_to_string(source_dir.join("lib.rs"));
+        fs::remove_dir_all(&source_dir).unwrap();
+
+        assert!(created.is_ok());
+        assert_eq!(created_input.unwrap(), "");
+        assert!(fetched.is_ok());
+        assert_eq!(fetched_input.unwrap(), "............\n");
+        assert!(module
+            .unwrap()
+            .contains("fetch::selected_input_path(args, 8)"));
+        assert!(lib.unwrap().contains("pub mod day_8;\n"));
+    }
+}
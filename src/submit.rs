@@ -0,0 +1,661 @@
+use crate::cli::Args;
+use crate::fetch::{Endpoint, SESSION_VARIABLE};
+use crate::http::{HttpError, Request};
+use crate::output::Answer;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_HISTORY_PATH: &str = "./answer_history.tsv";
+
+/// Submits the answer to `--part` of `--day`, either given with `--answer` or computed by
+/// `solve`. Every attempt is recorded in the history file at `--history`, and answers the history
+/// already rules out are not submitted.
+pub fn submit<F>(args: &Args, solve: F) -> Result<(), SubmitError>
+where
+    F: FnOnce() -> Vec<Answer>,
+{
+    let day: u8 = args.value("day").expect("--day is required to submit");
+    let part: u8 = args.value("part").expect("--part is required to submit");
+    assert!(part == 1 || part == 2, "--part must be 1 or 2");
+
+    let answer = match args.value::<String>("answer") {
+        Some(answer) => answer,
+        None => {
+            solve()
+                .into_iter()
+                .find(|answer| answer.day == day && answer.puzzle == part)
+                .ok_or(SubmitError::MissingAnswer)?
+                .value
+        }
+    };
+    let answer = answer.trim().to_string();
+    if answer.is_empty() || answer.contains(char::is_whitespace) {
+        return Err(SubmitError::InvalidAnswer(answer));
+    }
+
+    let history_path = args
+        .value("history")
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_HISTORY_PATH));
+    let mut history = History::read(history_path)?;
+
+    let now = unix_time();
+    history
+        .check(day, part, &answer, now)
+        .map_err(|refusal| SubmitError::Refused {
+            answer: answer.clone(),
+            refusal,
+        })?;
+
+    let endpoint = Endpoint::from_args(args).ok_or(SubmitError::MissingSession)?;
+    let url = format!("{}/answer", endpoint.day_url(day));
+    let level = part.to_string();
+    let request = Request::post_form(&url, &[("level", &level), ("answer", &answer)]);
+    let response = endpoint.request(request).send()?;
+
+    if response.status != 200 {
+        return Err(SubmitError::Status {
+            status: response.status,
+            body: response.body,
+        });
+    }
+
+    let verdict = Verdict::parse(&response.body);
+    history.append(Attempt {
+        timestamp: now,
+        day,
+        part,
+        answer: answer.clone(),
+        verdict,
+    })?;
+
+    println!("Day {} part {}: {} is {}", day, part, answer, verdict);
+
+    if matches!(verdict, Verdict::TooHigh | Verdict::TooLow) {
+        println!("{}", history.bounds(day, part));
+    }
+
+    Ok(())
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the unix epoch")
+        .as_secs()
+}
+
+/// What the server said about an answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Correct,
+    TooHigh,
+    TooLow,
+    /// Wrong, without a hint in which direction.
+    Wrong,
+    /// Rate limited, with the number of seconds left to wait if the server says so.
+    Wait(Option<u64>),
+    /// The part is already solved, or not unlocked yet.
+    WrongLevel,
+    Unknown,
+}
+
+impl Verdict {
+    fn parse(body: &str) -> Verdict {
+        if body.contains("That's the right answer") {
+            Verdict::Correct
+        } else if body.contains("You gave an answer too recently") {
+            Verdict::Wait(parse_wait(body))
+        } else if body.contains("your answer is too high") {
+            Verdict::TooHigh
+        } else if body.contains("your answer is too low") {
+            Verdict::TooLow
+        } else if body.contains("That's not the right answer") {
+            Verdict::Wrong
+        } else if body.contains("You don't seem to be solving the right level") {
+            Verdict::WrongLevel
+        } else {
+            Verdict::Unknown
+        }
+    }
+
+    fn is_wrong(&self) -> bool {
+        matches!(self, Verdict::TooHigh | Verdict::TooLow | Verdict::Wrong)
+    }
+
+    fn to_field(self) -> String {
+        match self {
+            Verdict::Correct => "correct".to_string(),
+            Verdict::TooHigh => "too-high".to_string(),
+            Verdict::TooLow => "too-low".to_string(),
+            Verdict::Wrong => "wrong".to_string(),
+            Verdict::Wait(None) => "wait".to_string(),
+            Verdict::Wait(Some(seconds)) => format!("wait:{}", seconds),
+            Verdict::WrongLevel => "wrong-level".to_string(),
+            Verdict::Unknown => "unknown".to_string(),
+        }
+    }
+
+    fn from_field(field: &str) -> Option<Verdict> {
+        let verdict = match field {
+            "correct" => Verdict::Correct,
+            "too-high" => Verdict::TooHigh,
+            "too-low" => Verdict::TooLow,
+            "wrong" => Verdict::Wrong,
+            "wait" => Verdict::Wait(None),
+            "wrong-level" => Verdict::WrongLevel,
+            "unknown" => Verdict::Unknown,
+            _ => Verdict::Wait(Some(field.strip_prefix("wait:")?.parse().ok()?)),
+        };
+
+        Some(verdict)
+    }
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Correct => write!(f, "correct"),
+            Verdict::TooHigh => write!(f, "too high"),
+            Verdict::TooLow => write!(f, "too low"),
+            Verdict::Wrong => write!(f, "wrong"),
+            Verdict::Wait(None) => write!(f, "not checked, submitted too recently"),
+            Verdict::Wait(Some(seconds)) => {
+                write!(f, "not checked, submitted too recently, wait {}s", seconds)
+            }
+            Verdict::WrongLevel => write!(f, "not checked, the part is solved or locked"),
+            Verdict::Unknown => write!(f, "not understood by the server response"),
+        }
+    }
+}
+
+/// Parses the time left from a message like "You have 1m 23s left to wait".
+fn parse_wait(body: &str) -> Option<u64> {
+    let start = body.find("You have ")? + "You have ".len();
+    let end = start + body[start..].find(" left to wait")?;
+
+    let mut seconds = 0;
+    for part in body[start..end].split_whitespace() {
+        seconds += if let Some(minutes) = part.strip_suffix('m') {
+            minutes.parse::<u64>().ok()? * 60
+        } else {
+            part.strip_suffix('s')?.parse::<u64>().ok()?
+        };
+    }
+
+    Some(seconds)
+}
+
+/// A submitted answer, stored as one tab separated line of the history file.
+#[derive(Debug, Clone)]
+struct Attempt {
+    timestamp: u64,
+    day: u8,
+    part: u8,
+    answer: String,
+    verdict: Verdict,
+}
+
+impl Attempt {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            self.timestamp,
+            self.day,
+            self.part,
+            self.answer,
+            self.verdict.to_field()
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Attempt> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [timestamp, day, part, answer, verdict] = fields[..] else {
+            return None;
+        };
+
+        Some(Attempt {
+            timestamp: timestamp.parse().ok()?,
+            day: day.parse().ok()?,
+            part: part.parse().ok()?,
+            answer: answer.to_string(),
+            verdict: Verdict::from_field(verdict)?,
+        })
+    }
+}
+
+struct History {
+    path: PathBuf,
+    attempts: Vec<Attempt>,
+}
+
+impl History {
+    /// Reads the history, which is empty if the file does not exist yet.
+    fn read(path: PathBuf) -> Result<History, SubmitError> {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(SubmitError::Io(error)),
+        };
+
+        let mut attempts = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let attempt = Attempt::from_line(line).ok_or(SubmitError::InvalidHistory {
+                path: path.clone(),
+                line: i + 1,
+            })?;
+            attempts.push(attempt);
+        }
+
+        Ok(History { path, attempts })
+    }
+
+    fn append(&mut self, attempt: Attempt) -> Result<(), SubmitError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(attempt.to_line().as_bytes())?;
+        self.attempts.push(attempt);
+
+        Ok(())
+    }
+
+    fn attempts(&self, day: u8, part: u8) -> impl Iterator<Item = &Attempt> {
+        self.attempts
+            .iter()
+            .filter(move |attempt| attempt.day == day && attempt.part == part)
+    }
+
+    /// The range numeric answers have to be in, given the answers that were too low or too high.
+    fn bounds(&self, day: u8, part: u8) -> Bounds {
+        let mut bounds = Bounds {
+            too_low: None,
+            too_high: None,
+        };
+
+        for attempt in self.attempts(day, part) {
+            let Ok(answer) = attempt.answer.parse::<i128>() else {
+                continue;
+            };
+
+            match attempt.verdict {
+                Verdict::TooLow => bounds.too_low = bounds.too_low.max(Some(answer)),
+                Verdict::TooHigh => {
+                    bounds.too_high = Some(bounds.too_high.map_or(answer, |high| high.min(answer)))
+                }
+                _ => {}
+            }
+        }
+
+        bounds
+    }
+
+    /// Checks whether `answer` is worth submitting at time `now`.
+    fn check(&self, day: u8, part: u8, answer: &str, now: u64) -> Result<(), Refusal> {
+        if let Some(correct) = self
+            .attempts(day, part)
+            .find(|attempt| attempt.verdict == Verdict::Correct)
+        {
+            return Err(Refusal::AlreadyCorrect(correct.answer.clone()));
+        }
+
+        if let Some(wrong) = self
+            .attempts(day, part)
+            .find(|attempt| attempt.answer == answer && attempt.verdict.is_wrong())
+        {
+            return Err(Refusal::KnownWrong(wrong.verdict));
+        }
+
+        let bounds = self.bounds(day, part);
+        if let Ok(answer) = answer.parse::<i128>() {
+            if !bounds.contains(answer) {
+                return Err(Refusal::OutOfBounds(bounds));
+            }
+        }
+
+        // the server rate limits all submissions, not just those for a single day
+        let wait_until = self
+            .attempts
+            .iter()
+            .filter_map(|attempt| match attempt.verdict {
+                Verdict::Wait(Some(seconds)) => Some(attempt.timestamp + seconds),
+                _ => None,
+            })
+            .max();
+        if let Some(wait_until) = wait_until.filter(|wait_until| *wait_until > now) {
+            return Err(Refusal::Wait(wait_until - now));
+        }
+
+        Ok(())
+    }
+}
+
+/// Exclusive bounds for numeric answers.
+#[derive(Debug, Clone, Copy)]
+pub struct Bounds {
+    too_low: Option<i128>,
+    too_high: Option<i128>,
+}
+
+impl Bounds {
+    fn contains(&self, answer: i128) -> bool {
+        self.too_low.is_none_or(|too_low| answer > too_low)
+            && self.too_high.is_none_or(|too_high| answer < too_high)
+    }
+}
+
+impl Display for Bounds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (self.too_low, self.too_high) {
+            (Some(too_low), Some(too_high)) => {
+                write!(f, "the answer is between {} and {}", too_low, too_high)
+            }
+            (Some(too_low), None) => write!(f, "the answer is greater than {}", too_low),
+            (None, Some(too_high)) => write!(f, "the answer is less than {}", too_high),
+            (None, None) => write!(f, "the answer is not bounded yet"),
+        }
+    }
+}
+
+/// Why an answer is not submitted.
+#[derive(Debug)]
+pub enum Refusal {
+    AlreadyCorrect(String),
+    KnownWrong(Verdict),
+    OutOfBounds(Bounds),
+    Wait(u64),
+}
+
+impl Display for Refusal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Refusal::AlreadyCorrect(answer) => {
+                write!(f, "the part is already solved with {}", answer)
+            }
+            Refusal::KnownWrong(verdict) => {
+                write!(f, "it was already submitted and is {}", verdict)
+            }
+            Refusal::OutOfBounds(bounds) => write!(f, "{}", bounds),
+            Refusal::Wait(seconds) => write!(f, "wait {}s before submitting again", seconds),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SubmitError {
+    MissingAnswer,
+    InvalidAnswer(String),
+    MissingSession,
+    Refused { answer: String, refusal: Refusal },
+    Http(HttpError),
+    Status { status: u16, body: String },
+    InvalidHistory { path: PathBuf, line: usize },
+    Io(io::Error),
+}
+
+impl Display for SubmitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubmitError::MissingAnswer => write!(f, "the day did not print an answer for the part"),
+            SubmitError::InvalidAnswer(answer) => write!(f, "invalid answer '{}'", answer),
+            SubmitError::MissingSession => write!(
+                f,
+                "no session token, pass --session or set {}",
+                SESSION_VARIABLE
+            ),
+            SubmitError::Refused { answer, refusal } => {
+                write!(f, "not submitting {}: {}", answer, refusal)
+            }
+            SubmitError::Http(error) => write!(f, "{}", error),
+            SubmitError::Status { status, body } => {
+                write!(
+                    f,
+                    "server responded with status {}: {}",
+                    status,
+                    body.trim()
+                )
+            }
+            SubmitError::InvalidHistory { path, line } => {
+                write!(f, "invalid line {} in {}", line, path.display())
+            }
+            SubmitError::Io(error) => write!(f, "could not update the history: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+impl From<HttpError> for SubmitError {
+    fn from(error: HttpError) -> Self {
+        SubmitError::Http(error)
+    }
+}
+
+impl From<io::Error> for SubmitError {
+    fn from(error: io::Error) -> Self {
+        SubmitError::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::stub;
+    use std::{env, process};
+
+    fn attempt(timestamp: u64, part: u8, answer: &str, verdict: Verdict) -> Attempt {
+        Attempt {
+            timestamp,
+            day: 2,
+            part,
+            answer: answer.to_string(),
+            verdict,
+        }
+    }
+
+    fn history(attempts: Vec<Attempt>) -> History {
+        History {
+            path: PathBuf::new(),
+            attempts,
+        }
+    }
+
+    #[test]
+    fn parse_verdicts() {
+        let verdicts = [
+            (
+                "That's the right answer! You are one gold star closer.",
+                Verdict::Correct,
+            ),
+            (
+                "That's not the right answer; your answer is too high.",
+                Verdict::TooHigh,
+            ),
+            (
+                "That's not the right answer; your answer is too low.",
+                Verdict::TooLow,
+            ),
+            (
+                "That's not the right answer. If you're stuck, ...",
+                Verdict::Wrong,
+            ),
+            (
+                "You don't seem to be solving the right level.",
+                Verdict::WrongLevel,
+            ),
+            ("<html>Something else</html>", Verdict::Unknown),
+        ];
+
+        for (body, verdict) in verdicts {
+            assert_eq!(Verdict::parse(body), verdict, "{}", body);
+        }
+    }
+
+    #[test]
+    fn parse_waits() {
+        let body = "You gave an answer too recently; you have to wait after submitting an answer \
+                    before trying again. You have 1m 23s left to wait.";
+        assert_eq!(Verdict::parse(body), Verdict::Wait(Some(83)));
+
+        assert_eq!(parse_wait("You have 45s left to wait."), Some(45));
+        assert_eq!(parse_wait("You have 2m left to wait."), Some(120));
+        assert_eq!(parse_wait("You have a while left to wait."), None);
+        assert_eq!(
+            Verdict::parse("You gave an answer too recently."),
+            Verdict::Wait(None)
+        );
+    }
+
+    #[test]
+    fn attempt_lines() {
+        let verdicts = [
+            Verdict::Correct,
+            Verdict::TooHigh,
+            Verdict::TooLow,
+            Verdict::Wrong,
+            Verdict::Wait(None),
+            Verdict::Wait(Some(83)),
+            Verdict::WrongLevel,
+            Verdict::Unknown,
+        ];
+
+        for verdict in verdicts {
+            let line = attempt(1733100000, 1, "-42", verdict).to_line();
+            let parsed = Attempt::from_line(line.trim_end()).expect("line does not parse");
+
+            assert_eq!(parsed.to_line(), line);
+            assert_eq!(parsed.verdict, verdict);
+        }
+
+        assert!(Attempt::from_line("1733100000\t2\t1\t42").is_none());
+        assert!(Attempt::from_line("1733100000\t2\t1\t42\tmaybe").is_none());
+    }
+
+    #[test]
+    fn refuse_solved_part() {
+        let history = history(vec![attempt(0, 1, "379", Verdict::Correct)]);
+
+        assert!(matches!(
+            history.check(2, 1, "380", 100),
+            Err(Refusal::AlreadyCorrect(answer)) if answer == "379"
+        ));
+        assert!(history.check(2, 2, "430", 100).is_ok());
+    }
+
+    #[test]
+    fn refuse_known_wrong_answer() {
+        let history = history(vec![attempt(0, 1, "abc", Verdict::Wrong)]);
+
+        assert!(matches!(
+            history.check(2, 1, "abc", 100),
+            Err(Refusal::KnownWrong(Verdict::Wrong))
+        ));
+        assert!(history.check(2, 1, "abd", 100).is_ok());
+    }
+
+    #[test]
+    fn refuse_out_of_bounds() {
+        let history = history(vec![
+            attempt(0, 1, "100", Verdict::TooLow),
+            attempt(0, 1, "500", Verdict::TooHigh),
+            attempt(0, 1, "400", Verdict::TooHigh),
+        ]);
+
+        assert!(matches!(
+            history.check(2, 1, "100", 100),
+            Err(Refusal::KnownWrong(Verdict::TooLow))
+        ));
+        assert!(matches!(
+            history.check(2, 1, "50", 100),
+            Err(Refusal::OutOfBounds(_))
+        ));
+        assert!(matches!(
+            history.check(2, 1, "450", 100),
+            Err(Refusal::OutOfBounds(_))
+        ));
+        assert!(history.check(2, 1, "399", 100).is_ok());
+        assert_eq!(
+            history.bounds(2, 1).to_string(),
+            "the answer is between 100 and 400"
+        );
+    }
+
+    #[test]
+    fn refuse_within_wait() {
+        let history = history(vec![attempt(1000, 2, "430", Verdict::Wait(Some(60)))]);
+
+        // the wait applies to all days and parts
+        assert!(matches!(
+            history.check(3, 1, "1", 1045),
+            Err(Refusal::Wait(15))
+        ));
+        assert!(history.check(3, 1, "1", 1060).is_ok());
+    }
+
+    #[test]
+    fn submit_to_stub_server() {
+        let (base_url, server) = stub::serve_once(200, "<p>That's the right answer!</p>");
+        let history_path =
+            env::temp_dir().join(format!("advent-of-code-history-{}.tsv", process::id()));
+        let args = Args::parse(
+            [
+                "submit",
+                "--day",
+                "2",
+                "--part",
+                "2",
+                "--base-url",
+                &base_url,
+                "--session",
+                "abc",
+                "--history",
+                history_path.to_str().unwrap(),
+            ]
+            .into_iter()
+            .map(String::from),
+        );
+        let solve = || {
+            vec![
+                Answer {
+                    day: 2,
+                    puzzle: 1,
+                    value: "379".to_string(),
+                },
+                Answer {
+                    day: 2,
+                    puzzle: 2,
+                    value: "430".to_string(),
+                },
+            ]
+        };
+
+        let submitted = submit(&args, solve);
+        let request = server.join().unwrap();
+        let history = History::read(history_path.clone());
+
+        // the stub server is gone, so the part has to be refused before connecting
+        let submitted_again = submit(&args, solve);
+        fs::remove_file(&history_path).unwrap();
+
+        assert!(submitted.is_ok());
+        assert!(request.starts_with("POST /2024/day/2/answer HTTP/1.0\r\n"));
+        assert!(request.contains("\r\nCookie: session=abc\r\n"));
+        assert!(request.ends_with("\r\n\r\nlevel=2&answer=430"));
+
+        let attempts: Vec<Attempt> = history.unwrap().attempts(2, 2).cloned().collect();
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].answer, "430");
+        assert_eq!(attempts[0].verdict, Verdict::Correct);
+
+        assert!(matches!(
+            submitted_again,
+            Err(SubmitError::Refused {
+                refusal: Refusal::AlreadyCorrect(_),
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::cli::Args;
+use crate::fetch;
+use crate::output::{self, Answer};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const DEFAULT_POLL_INTERVAL_MS: u64 = 500;
+
+/// The modification time and length of every watched file that exists.
+type Snapshot = BTreeMap<PathBuf, (SystemTime, u64)>;
+
+/// Runs `solve` for `--day` on its input and on each of its example files, and again on every
+/// file that changes, showing how the answers differ from the previous run on that file. The
+/// input is `--input` if given. Files are polled every `--poll-interval` milliseconds. Runs until
+/// interrupted.
+///
+/// Only input files are watched, not the source of the solvers, as a changed solver needs to be
+/// rebuilt first.
+pub fn watch<F: Fn(&Args)>(args: &Args, solve: F) {
+    let day: u8 = args.value("day").expect("--watch needs --day");
+    let poll_interval = Duration::from_millis(
+        args.value("poll-interval")
+            .unwrap_or(DEFAULT_POLL_INTERVAL_MS),
+    );
+
+    let input_path = fetch::selected_input_path(args, day);
+    let mut changed = watched_files(&input_path, day);
+    let mut snapshot = take_snapshot(&changed);
+    let mut previous_answers: HashMap<PathBuf, Vec<Answer>> = HashMap::new();
+
+    loop {
+        for path in changed {
+            println!("Solving day {} for {}", day, path.display());
+
+            let args = args.with_value("input", &path.to_string_lossy());
+            // a panicking solver, e.g. on a half written input, must not end the watch
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| solve(&args)));
+            let answers = output::take_answers();
+
+            if let Some(previous_answers) = previous_answers.get(&path) {
+                print_diff(previous_answers, &answers);
+            }
+            previous_answers.insert(path, answers);
+        }
+
+        println!("Watching the input and examples of day {}", day);
+
+        changed = loop {
+            thread::sleep(poll_interval);
+
+            let next_snapshot = take_snapshot(&watched_files(&input_path, day));
+            if next_snapshot != snapshot {
+                let changed = changed_files(&snapshot, &next_snapshot);
+                snapshot = next_snapshot;
+                break changed;
+            }
+        };
+    }
+}
+
+/// `input_path` and every `day_N_example*` file next to the input of the day.
+fn watched_files(input_path: &Path, day: u8) -> Vec<PathBuf> {
+    let mut files = vec![input_path.to_path_buf()];
+
+    let example_prefix = format!("day_{}_example", day);
+    let day_input_path = fetch::input_path(day);
+    let resources = day_input_path.parent().expect("inputs are in a directory");
+    if let Ok(entries) = fs::read_dir(resources) {
+        let mut examples: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(&example_prefix)
+            })
+            .map(|entry| entry.path())
+            .filter(|path| path != input_path)
+            .collect();
+        examples.sort();
+        files.extend(examples);
+    }
+
+    files
+}
+
+fn take_snapshot(files: &[PathBuf]) -> Snapshot {
+    files
+        .iter()
+        .filter_map(|path| {
+            let metadata = fs::metadata(path).ok()?;
+            Some((path.clone(), (metadata.modified().ok()?, metadata.len())))
+        })
+        .collect()
+}
+
+/// The files that were changed or created between two snapshots. Deleted files are left out, as
+/// there is nothing to solve.
+fn changed_files(before: &Snapshot, after: &Snapshot) -> Vec<PathBuf> {
+    after
+        .iter()
+        .filter(|(path, state)| before.get(*path) != Some(*state))
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+fn print_diff(previous_answers: &[Answer], answers: &[Answer]) {
+    println!("Compared to the previous run:");
+
+    let find = |answers: &[Answer], puzzle: u8| {
+        answers
+            .iter()
+            .find(|answer| answer.puzzle == puzzle)
+            .map(|answer| answer.value.clone())
+    };
+
+    let mut puzzles: Vec<u8> = previous_answers
+        .iter()
+        .chain(answers)
+        .map(|answer| answer.puzzle)
+        .collect();
+    puzzles.sort_unstable();
+    puzzles.dedup();
+
+    for puzzle in puzzles {
+        let missing = || "no answer".to_string();
+        let previous = find(previous_answers, puzzle).unwrap_or_else(missing);
+        let current = find(answers, puzzle).unwrap_or_else(missing);
+
+        if previous == current {
+            println!("Puzzle {}: {} (unchanged)", puzzle, current);
+        } else {
+            println!("Puzzle {}: {} -> {}", puzzle, previous, current);
+        }
+    }
+}